@@ -0,0 +1,310 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::auth::Auth;
+use crate::i18n;
+
+pub const DEFAULT_API_URL: &str = "https://qianfan.baidubce.com/v2/chat/completions";
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+pub struct ChatReply {
+    pub content: String,
+    pub usage: Option<Usage>,
+    /// Time taken by the attempt that succeeded, without earlier attempts and backoff
+    pub latency: Duration,
+}
+
+pub fn build_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .danger_accept_invalid_certs(true)
+        .no_proxy()
+        .build()
+        .unwrap_or_default()
+}
+
+pub fn build_payload(model: &str, messages: Vec<Value>) -> Value {
+    serde_json::json!({
+        "model": model,
+        "messages": messages
+    })
+}
+
+/// Merges extra request body fields such as `temperature` into `payload`,
+/// leaving `model` and `messages` alone.
+pub fn apply_params(payload: &mut Value, params: &serde_json::Map<String, Value>) {
+    if let Some(object) = payload.as_object_mut() {
+        for (key, value) in params {
+            if key != "model" && key != "messages" {
+                object.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// The request as a curl command. The token is left as `$TOKEN` so the command
+/// can be shared or logged without leaking it.
+pub fn format_curl_command(api_url: &str, payload: &Value) -> String {
+    let json_str = serde_json::to_string_pretty(payload).unwrap_or_default()
+        .replace('\n', "\n    ")
+        .replace('\'', "'\\''");
+
+    format!(
+        "curl -X POST '{}' -H 'Content-Type: application/json' -H \"Authorization: Bearer $TOKEN\" -d '{}'",
+        api_url,
+        json_str
+    )
+}
+
+pub struct RetryAttempt {
+    /// The attempt that is about to be made (2 for the first retry)
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay: Duration,
+    pub error: String,
+}
+
+struct RequestError {
+    message: String,
+    retryable: bool,
+    retry_after: Option<Duration>,
+    // The token was rejected
+    unauthorized: bool,
+}
+
+impl RequestError {
+    fn fatal(message: String) -> Self {
+        RequestError { message, retryable: false, retry_after: None, unauthorized: false }
+    }
+}
+
+/// Error from `send_chat` when the API rejected the bearer token.
+#[derive(Debug)]
+pub struct Unauthorized(String);
+
+impl fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Sends a chat completion request and extracts the reply, turning both
+/// transport failures and API error bodies into errors. Timeouts, 5xx responses
+/// and rate limits are retried up to `max_retries` times with jittered
+/// exponential backoff; `on_retry` is called before each retry.
+pub async fn send_chat(
+    client: &reqwest::Client,
+    api_url: &str,
+    auth_token: &str,
+    payload: &Value,
+    max_retries: u32,
+    mut on_retry: impl FnMut(&RetryAttempt),
+) -> Result<ChatReply> {
+    let max_attempts = max_retries + 1;
+    let mut attempt = 1;
+    loop {
+        match send_once(client, api_url, auth_token, payload).await {
+            Ok(reply) => return Ok(reply),
+            Err(e) if e.retryable && attempt < max_attempts => {
+                let delay = e.retry_after
+                    .unwrap_or_else(|| backoff_delay(attempt))
+                    .min(MAX_RETRY_DELAY);
+                attempt += 1;
+                on_retry(&RetryAttempt { attempt, max_attempts, delay, error: e.message });
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) if e.unauthorized => return Err(Unauthorized(e.message).into()),
+            Err(e) if attempt > 1 => bail!("{}", (i18n::text().attempts)(&e.message, attempt)),
+            Err(e) => bail!("{}", e.message),
+        }
+    }
+}
+
+/// `send_chat` with `token` from `auth`. If the API rejects a token obtained
+/// with AK/SK, e.g. one revoked before it expired, the cached token is dropped
+/// and the request is sent once more with a new one.
+pub async fn send_chat_as(
+    client: &reqwest::Client,
+    api_url: &str,
+    auth: &Auth,
+    token: &str,
+    payload: &Value,
+    max_retries: u32,
+    mut on_retry: impl FnMut(&RetryAttempt),
+) -> Result<ChatReply> {
+    match send_chat(client, api_url, token, payload, max_retries, &mut on_retry).await {
+        Err(e) if e.is::<Unauthorized>() && auth.invalidate(token).await => {
+            let token = auth.bearer_token(client).await?;
+            send_chat(client, api_url, &token, payload, max_retries, on_retry).await
+        }
+        result => result,
+    }
+}
+
+// Exponential backoff with jitter: a random delay between half and all of
+// BASE_RETRY_DELAY * 2^(attempt - 1)
+fn backoff_delay(attempt: u32) -> Duration {
+    let exponential = BASE_RETRY_DELAY.saturating_mul(1 << (attempt - 1).min(6));
+    exponential.mul_f64(0.5 + random_fraction() / 2.0)
+}
+
+fn random_fraction() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
+    // RandomState is seeded randomly per instance, which is enough for jitter
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u128(std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos());
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+async fn send_once(client: &reqwest::Client, api_url: &str, auth_token: &str, payload: &Value) -> Result<ChatReply, RequestError> {
+    let started = Instant::now();
+    let response = client
+        .post(api_url)
+        .header("Content-Type", "application/json")
+        .header("Authorization", format!("Bearer {}", auth_token))
+        .json(payload)
+        .send()
+        .await
+        .map_err(|e| RequestError {
+            retryable: e.is_timeout() || e.is_connect(),
+            message: (i18n::text().request_error)(&e.to_string()),
+            retry_after: None,
+            unauthorized: false,
+        })?;
+
+    let status = response.status();
+    let retry_after = retry_after(response.headers());
+    let status_retryable = status.as_u16() == 429 || status.is_server_error();
+    let unauthorized = status == reqwest::StatusCode::UNAUTHORIZED;
+    let text = response.text().await
+        .map_err(|e| RequestError {
+            retryable: e.is_timeout(),
+            message: (i18n::text().parse_error)(&e.to_string()),
+            retry_after: None,
+            unauthorized: false,
+        })?;
+    let json: Value = match serde_json::from_str(&text) {
+        Ok(json) => json,
+        Err(_) if !status.is_success() => return Err(RequestError {
+            message: format!("HTTP {}: {}", status, text.trim()),
+            retryable: status_retryable,
+            retry_after,
+            unauthorized,
+        }),
+        Err(e) => return Err(RequestError::fatal((i18n::text().parse_error)(&e.to_string()))),
+    };
+
+    if let Some(error) = api_error(&json) {
+        return Err(RequestError {
+            message: (i18n::text().api_error)(&error.message),
+            retryable: error.transient || status_retryable,
+            retry_after,
+            unauthorized: error.invalid_token || unauthorized,
+        });
+    }
+    if !status.is_success() {
+        return Err(RequestError {
+            message: format!("HTTP {}: {}", status, text.trim()),
+            retryable: status_retryable,
+            retry_after,
+            unauthorized,
+        });
+    }
+
+    let content = json["choices"][0]["message"]["content"].as_str()
+        .ok_or_else(|| RequestError::fatal((i18n::text().no_content)(text.trim())))?;
+    let usage = serde_json::from_value(json["usage"].clone()).ok();
+
+    Ok(ChatReply {
+        content: content.to_string(),
+        usage,
+        latency: started.elapsed(),
+    })
+}
+
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    // HTTP-date form
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now()).to_std().ok()
+}
+
+// Qianfan v1 error codes worth retrying: service unavailable, cluster over
+// capacity, QPS limit, internal error, RPM/TPM limits
+const TRANSIENT_ERROR_CODES: [i64; 6] = [2, 4, 18, 336100, 336501, 336502];
+
+// Qianfan v1 error codes for an invalid or expired access token
+const INVALID_TOKEN_ERROR_CODES: [i64; 2] = [110, 111];
+
+struct ApiError {
+    message: String,
+    transient: bool,
+    invalid_token: bool,
+}
+
+// Qianfan v2 returns {"error": {"code", "message", "type"}}, v1 returns
+// {"error_code", "error_msg"}
+fn api_error(json: &Value) -> Option<ApiError> {
+    if let Some(error) = json.get("error").filter(|e| !e.is_null()) {
+        let code = error["code"].as_str().map(str::to_string)
+            .or_else(|| error["code"].as_i64().map(|c| c.to_string()))
+            .unwrap_or_default();
+        let kind = error["type"].as_str().unwrap_or_default();
+        let transient = code.contains("rate_limit") || kind.contains("rate_limit")
+            || code == "internal_error" || kind == "server_error";
+        let invalid_token = code == "invalid_iam_token" || code == "invalid_token";
+        let message = error["message"].as_str().unwrap_or(i18n::text().unknown_error);
+        let message = if code.is_empty() { message.to_string() } else { format!("[{}] {}", code, message) };
+        return Some(ApiError { message, transient, invalid_token });
+    }
+    json.get("error_code").and_then(Value::as_i64).map(|code| ApiError {
+        message: format!("[{}] {}", code, json["error_msg"].as_str().unwrap_or(i18n::text().unknown_error)),
+        transient: TRANSIENT_ERROR_CODES.contains(&code),
+        invalid_token: INVALID_TOKEN_ERROR_CODES.contains(&code),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classifies_v2_errors() {
+        let error = api_error(&json!({ "error": { "code": "rate_limit_exceeded", "message": "slow down", "type": "rate_limit_error" } })).unwrap();
+        assert!(error.transient && !error.invalid_token);
+        assert_eq!(error.message, "[rate_limit_exceeded] slow down");
+        let error = api_error(&json!({ "error": { "code": "invalid_iam_token", "message": "bad token" } })).unwrap();
+        assert!(!error.transient && error.invalid_token);
+        assert!(api_error(&json!({ "error": null, "choices": [] })).is_none());
+    }
+
+    #[test]
+    fn classifies_v1_errors() {
+        let error = api_error(&json!({ "error_code": 18, "error_msg": "qps limit" })).unwrap();
+        assert!(error.transient && !error.invalid_token);
+        assert_eq!(error.message, "[18] qps limit");
+        let error = api_error(&json!({ "error_code": 111, "error_msg": "token expired" })).unwrap();
+        assert!(!error.transient && error.invalid_token);
+    }
+}
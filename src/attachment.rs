@@ -0,0 +1,275 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n;
+use crate::import;
+use crate::paths;
+
+const MAX_IMAGE_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_TEXT_BYTES: u64 = 256 * 1024;
+
+const IMAGE_TYPES: [(&str, &str); 6] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+];
+
+/// A file attached to a user message. Text is kept with the message and images
+/// are stored once under the data dir, so a saved session can be sent again
+/// without carrying the image data in its JSON.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Attachment {
+    Image {
+        name: String,
+        mime: String,
+        // Hex SHA-256 of the image, its file name under `images_dir`
+        sha256: String,
+        size: u64,
+    },
+    Text {
+        name: String,
+        content: String,
+    },
+    // Output of a shell command run from the input
+    Command {
+        command: String,
+        output: String,
+        // `None` if the command was killed by a signal
+        exit_code: Option<i32>,
+    },
+}
+
+impl Attachment {
+    /// Reads an image (by extension) or a UTF-8 text file.
+    pub fn load(path: &str) -> Result<Attachment> {
+        let text = i18n::text();
+        let path = import::expand_tilde(path.trim());
+        let name = path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+
+        if let Some(mime) = image_type(&path) {
+            if file_size(&path)? > MAX_IMAGE_BYTES {
+                bail!("{}", (text.attachment_too_large)(&name, &format_size(MAX_IMAGE_BYTES)));
+            }
+            let bytes = fs::read(&path).with_context(|| (text.import_read_error)(&path.display()))?;
+            let sha256 = format!("{:x}", Sha256::digest(&bytes));
+            let stored = images_dir().join(&sha256);
+            if !stored.exists() {
+                fs::create_dir_all(images_dir())?;
+                fs::write(&stored, &bytes).with_context(|| (text.attachment_store_error)(&stored.display()))?;
+            }
+            return Ok(Attachment::Image { name, mime: mime.to_string(), sha256, size: bytes.len() as u64 });
+        }
+        read_text(&path, name)
+    }
+
+    /// Reads `path` as a UTF-8 text file, shown to the model as `name`.
+    pub fn load_text(path: &str, name: &str) -> Result<Attachment> {
+        read_text(&import::expand_tilde(path), name.to_string())
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Attachment::Image { name, .. } | Attachment::Text { name, .. } => name,
+            Attachment::Command { command, .. } => command,
+        }
+    }
+
+    pub fn is_image(&self) -> bool {
+        matches!(self, Attachment::Image { .. })
+    }
+
+    /// Size of the original file, for display.
+    pub fn size(&self) -> String {
+        let bytes = match self {
+            Attachment::Image { size, .. } => *size,
+            Attachment::Text { content, .. } => content.len() as u64,
+            Attachment::Command { output, .. } => output.len() as u64,
+        };
+        format_size(bytes)
+    }
+
+    /// The fenced block inlined before the prompt, `None` for images.
+    pub fn inline_text(&self) -> Option<String> {
+        let text = i18n::text();
+        match self {
+            Attachment::Image { .. } => None,
+            Attachment::Text { name, content } => Some(fenced(&(text.attachment_header)(name), content)),
+            Attachment::Command { command, output, exit_code } => {
+                Some(fenced(&(text.command_header)(command, *exit_code), output))
+            }
+        }
+    }
+}
+
+/// The last `lines` lines of command output, with its header, for the history.
+/// `None` for other attachments.
+pub fn command_preview(attachment: &Attachment, lines: usize) -> Option<String> {
+    let Attachment::Command { command, output, exit_code } = attachment else {
+        return None;
+    };
+    let text = i18n::text();
+    let all: Vec<&str> = output.lines().collect();
+    let tail = &all[all.len().saturating_sub(lines)..];
+    let mut preview = fenced(&(text.command_header)(command, *exit_code), &tail.join("\n"));
+    preview.push('\n');
+    preview.push_str(&(text.command_attached)(all.len()));
+    Some(preview)
+}
+
+pub fn images_dir() -> PathBuf {
+    paths::data_dir().join("images")
+}
+
+fn read_text(path: &Path, name: String) -> Result<Attachment> {
+    let text = i18n::text();
+    if file_size(path)? > MAX_TEXT_BYTES {
+        bail!("{}", (text.attachment_too_large)(&name, &format_size(MAX_TEXT_BYTES)));
+    }
+    let bytes = fs::read(path).with_context(|| (text.import_read_error)(&path.display()))?;
+    match String::from_utf8(bytes) {
+        Ok(content) if !content.contains('\0') => Ok(Attachment::Text { name, content }),
+        _ => bail!("{}", (text.attachment_binary)(&name)),
+    }
+}
+
+fn file_size(path: &Path) -> Result<u64> {
+    let meta = fs::metadata(path).with_context(|| (i18n::text().import_read_error)(&path.display()))?;
+    Ok(meta.len())
+}
+
+fn image_type(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    IMAGE_TYPES.iter().find(|(e, _)| *e == ext).map(|(_, mime)| *mime)
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// `content` in a fenced code block under `header`. The fence is longer than
+/// any backtick run in the content, so it cannot be closed early.
+pub fn fenced(header: &str, content: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat((longest + 1).max(3));
+    let newline = if content.ends_with('\n') { "" } else { "\n" };
+    format!("{}\n{}\n{}{}{}", header, fence, content, newline, fence)
+}
+
+/// The request `content` of a user message: text files and command output are
+/// inlined before the prompt, and images turn it into an array of `text` and
+/// `image_url` parts. The image data is filled in by `load_images`.
+pub fn request_content(prompt: &str, attachments: &[Attachment]) -> Value {
+    if attachments.is_empty() {
+        return Value::from(prompt);
+    }
+    let mut text = String::new();
+    for inline in attachments.iter().filter_map(Attachment::inline_text) {
+        text.push_str(&inline);
+        text.push_str("\n\n");
+    }
+    text.push_str(prompt);
+    if !attachments.iter().any(Attachment::is_image) {
+        return Value::from(text);
+    }
+
+    let mut parts = vec![serde_json::json!({ "type": "text", "text": text })];
+    for attachment in attachments {
+        if let Attachment::Image { mime, sha256, .. } = attachment {
+            parts.push(serde_json::json!({
+                "type": "image_url",
+                "image_url": { "url": "" },
+                "stored_image": { "mime": mime, "sha256": sha256 },
+            }));
+        }
+    }
+    Value::Array(parts)
+}
+
+/// Reads the stored images of `image_url` parts into data URLs, right before
+/// sending. Images deleted from the store are left out.
+pub fn load_images(messages: &mut [Value]) {
+    for message in messages {
+        let Some(parts) = message["content"].as_array_mut() else {
+            continue;
+        };
+        parts.retain_mut(|part| {
+            let Some(stored) = part.as_object_mut().and_then(|part| part.remove("stored_image")) else {
+                return true;
+            };
+            let sha256 = stored["sha256"].as_str().unwrap_or_default();
+            let Ok(bytes) = fs::read(images_dir().join(sha256)) else {
+                return false;
+            };
+            let mime = stored["mime"].as_str().unwrap_or_default();
+            part["image_url"]["url"] = Value::from(format!("data:{};base64,{}", mime, BASE64.encode(bytes)));
+            true
+        });
+    }
+}
+
+/// Turns `content` arrays back into their text, for models that do not accept
+/// images. Images attached to earlier turns would otherwise fail every request.
+pub fn without_images(messages: &mut [Value]) {
+    for message in messages {
+        let Some(parts) = message["content"].as_array() else {
+            continue;
+        };
+        let text: Vec<&str> = parts.iter()
+            .filter(|part| part["type"] == "text")
+            .filter_map(|part| part["text"].as_str())
+            .collect();
+        message["content"] = Value::from(text.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fence_is_longer_than_backtick_runs() {
+        assert_eq!(fenced("a.rs", "fn main() {}"), "a.rs\n```\nfn main() {}\n```");
+        assert_eq!(fenced("a.md", "```rust\nx\n```\n"), "a.md\n````\n```rust\nx\n```\n````");
+        assert_eq!(fenced("b", "`````"), "b\n``````\n`````\n``````");
+    }
+
+    #[test]
+    fn command_preview_shows_last_lines() {
+        let output: String = (1..=20).map(|n| format!("line {}\n", n)).collect();
+        let attachment = Attachment::Command { command: "seq".to_string(), output, exit_code: Some(0) };
+        let preview = command_preview(&attachment, 5).unwrap();
+        assert!(preview.contains("line 16\n") && preview.contains("line 20\n"));
+        assert!(!preview.contains("line 15\n"));
+        let text = Attachment::Text { name: "a.txt".to_string(), content: String::new() };
+        assert!(command_preview(&text, 5).is_none());
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
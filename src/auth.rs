@@ -0,0 +1,327 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::credentials;
+use crate::i18n;
+use crate::paths;
+
+pub const DEFAULT_IAM_URL: &str = "https://iam.bj.baidubce.com/v1/BCE-BEARER/token";
+
+// Environment variables used by the Qianfan SDKs
+const ACCESS_KEY_ENV: &str = "QIANFAN_ACCESS_KEY";
+const SECRET_KEY_ENV: &str = "QIANFAN_SECRET_KEY";
+
+// Lifetime requested for bearer tokens, and how long before expiry they are refreshed
+const TOKEN_LIFETIME_SECS: i64 = 24 * 60 * 60;
+const REFRESH_MARGIN_SECS: i64 = 5 * 60;
+// Validity of the signature on the token request
+const SIGNATURE_EXPIRATION_SECS: u32 = 1800;
+
+/// How requests are authorized: a bearer token configured directly, or a
+/// bearer token obtained from Baidu IAM with an access key / secret key pair.
+#[derive(Clone)]
+pub enum Auth {
+    None,
+    Bearer(String),
+    AkSk(Arc<BceTokenProvider>),
+}
+
+impl Auth {
+    /// The configured bearer token wins; otherwise AK/SK from the config or
+    /// the QIANFAN_ACCESS_KEY / QIANFAN_SECRET_KEY environment variables.
+    pub fn new(token: String, access_key: Option<&str>, secret_key: Option<&str>, iam_url: Option<&str>) -> Auth {
+        if !token.is_empty() {
+            return Auth::Bearer(token);
+        }
+        let access_key = access_key.map(str::to_string).or_else(|| std::env::var(ACCESS_KEY_ENV).ok());
+        let secret_key = secret_key.map(str::to_string).or_else(|| std::env::var(SECRET_KEY_ENV).ok());
+        match (access_key, secret_key) {
+            (Some(access_key), Some(secret_key)) if !access_key.is_empty() && !secret_key.is_empty() => {
+                Auth::AkSk(Arc::new(BceTokenProvider {
+                    access_key,
+                    secret_key,
+                    iam_url: iam_url.unwrap_or(DEFAULT_IAM_URL).to_string(),
+                    cached: Mutex::new(None),
+                }))
+            }
+            _ => Auth::None,
+        }
+    }
+
+    pub fn is_configured(&self) -> bool {
+        !matches!(self, Auth::None)
+    }
+
+    /// Drops `token` from the cache after the API rejected it. Returns whether
+    /// a new token can be obtained, which is only the case with AK/SK.
+    pub async fn invalidate(&self, token: &str) -> bool {
+        match self {
+            Auth::AkSk(provider) => {
+                provider.invalidate(token).await;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Bearer token for the next request, exchanging AK/SK for a fresh one
+    /// when the cached token is missing or about to expire.
+    pub async fn bearer_token(&self, client: &reqwest::Client) -> Result<String> {
+        match self {
+            Auth::None => bail!("{}", i18n::text().no_auth),
+            Auth::Bearer(token) => Ok(token.clone()),
+            Auth::AkSk(provider) => provider.token(client).await.context(i18n::text().aksk_failed),
+        }
+    }
+}
+
+pub struct BceTokenProvider {
+    access_key: String,
+    secret_key: String,
+    iam_url: String,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct CachedToken {
+    token: String,
+    // Unix timestamp
+    expires_at: i64,
+}
+
+impl CachedToken {
+    fn is_fresh(&self) -> bool {
+        self.expires_at - Utc::now().timestamp() > REFRESH_MARGIN_SECS
+    }
+}
+
+impl BceTokenProvider {
+    async fn token(&self, client: &reqwest::Client) -> Result<String> {
+        // Held across the exchange so concurrent requests share one refresh
+        let mut cached = self.cached.lock().await;
+        if cached.is_none() {
+            *cached = load_cached_token(&self.cache_path());
+        }
+        if let Some(token) = cached.as_ref().filter(|token| token.is_fresh()) {
+            return Ok(token.token.clone());
+        }
+
+        let token = self.request_token(client).await?;
+        // If the cache can't be written the token still works for this process
+        let _ = save_cached_token(&self.cache_path(), &token);
+        let bearer = token.token.clone();
+        *cached = Some(token);
+        Ok(bearer)
+    }
+
+    async fn invalidate(&self, token: &str) {
+        let mut cached = self.cached.lock().await;
+        // Another request may have replaced it already
+        if cached.as_ref().is_some_and(|cached| cached.token == token) {
+            *cached = None;
+            let _ = fs::remove_file(self.cache_path());
+        }
+    }
+
+    async fn request_token(&self, client: &reqwest::Client) -> Result<CachedToken> {
+        let mut url = reqwest::Url::parse(&self.iam_url).with_context(|| (i18n::text().iam_url_invalid)(&self.iam_url))?;
+        url.query_pairs_mut().append_pair("expireInSeconds", &TOKEN_LIFETIME_SECS.to_string());
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => bail!("{}", (i18n::text().iam_url_invalid)(&self.iam_url)),
+        };
+        let now = Utc::now();
+        let date = now.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let authorization = self.sign("GET", &url, &[("host", &host), ("x-bce-date", &date)], &date);
+
+        let response = client.get(url)
+            .header("Host", &host)
+            .header("x-bce-date", &date)
+            .header("Authorization", authorization)
+            .send()
+            .await
+            .map_err(|e| anyhow!("{}", (i18n::text().request_error)(&e.to_string())))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| anyhow!("{}", (i18n::text().parse_error)(&e.to_string())))?;
+        let json: Value = serde_json::from_str(&text).unwrap_or_default();
+        if !status.is_success() {
+            let message = json["message"].as_str().unwrap_or(text.trim());
+            match json["code"].as_str() {
+                Some(code) => bail!("HTTP {}: [{}] {}", status, code, message),
+                None => bail!("HTTP {}: {}", status, message),
+            }
+        }
+
+        let token = json["token"].as_str()
+            .ok_or_else(|| anyhow!("{}", (i18n::text().no_iam_token)(text.trim())))?;
+        let expires_at = json["expireTime"].as_str()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map_or(now.timestamp() + TOKEN_LIFETIME_SECS, |time| time.timestamp());
+        Ok(CachedToken {
+            token: token.to_string(),
+            expires_at,
+        })
+    }
+
+    // BCE auth v1 request signature:
+    // https://cloud.baidu.com/doc/Reference/s/njwvz1yfu
+    fn sign(&self, method: &str, url: &reqwest::Url, headers: &[(&str, &str)], date: &str) -> String {
+        let auth_prefix = format!("bce-auth-v1/{}/{}/{}", self.access_key, date, SIGNATURE_EXPIRATION_SECS);
+        let signing_key = hmac_sha256_hex(self.secret_key.as_bytes(), &auth_prefix);
+
+        let mut query: Vec<String> = url.query_pairs()
+            .filter(|(key, _)| key != "authorization")
+            .map(|(key, value)| format!("{}={}", uri_encode(&key, true), uri_encode(&value, true)))
+            .collect();
+        query.sort();
+        let mut canonical_headers: Vec<String> = headers.iter()
+            .map(|(name, value)| format!("{}:{}", uri_encode(&name.to_lowercase(), true), uri_encode(value.trim(), true)))
+            .collect();
+        canonical_headers.sort();
+        let mut signed_headers: Vec<String> = headers.iter().map(|(name, _)| name.to_lowercase()).collect();
+        signed_headers.sort();
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}",
+            method,
+            canonical_uri(url.path()),
+            query.join("&"),
+            canonical_headers.join("\n")
+        );
+        let signature = hmac_sha256_hex(signing_key.as_bytes(), &canonical_request);
+        format!("{}/{}/{}", auth_prefix, signed_headers.join(";"), signature)
+    }
+
+    // One file per access key and IAM endpoint, so profiles don't evict each other's tokens
+    fn cache_path(&self) -> PathBuf {
+        let digest = Sha256::digest(format!("{}\n{}", self.access_key, self.iam_url));
+        let name = digest[..8].iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{:02x}", byte);
+            hex
+        });
+        paths::cache_dir().join(format!("bce_token_{}.json", name))
+    }
+}
+
+fn hmac_sha256_hex(key: &[u8], data: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+// The path of a parsed URL is already percent-encoded, so each segment is
+// decoded before being encoded the way BCE expects
+fn canonical_uri(path: &str) -> String {
+    path.split('/')
+        .map(|segment| uri_encode(&percent_decode_str(segment).decode_utf8_lossy(), true))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Percent-encodes everything except unreserved characters (and `/` in paths)
+fn uri_encode(text: &str, encode_slash: bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(byte as char),
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => {
+                let _ = write!(encoded, "%{:02X}", byte);
+            }
+        }
+    }
+    encoded
+}
+
+fn load_cached_token(path: &Path) -> Option<CachedToken> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_cached_token(path: &Path, token: &CachedToken) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    credentials::write_private(path, serde_json::to_string(token)?.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> BceTokenProvider {
+        BceTokenProvider {
+            access_key: "ak".to_string(),
+            secret_key: "sk".to_string(),
+            iam_url: DEFAULT_IAM_URL.to_string(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn hmac_matches_known_vector() {
+        assert_eq!(
+            hmac_sha256_hex(b"key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn uri_encode_keeps_unreserved_characters() {
+        assert_eq!(uri_encode("aZ09-_.~", true), "aZ09-_.~");
+        assert_eq!(uri_encode("a b/c:d", true), "a%20b%2Fc%3Ad");
+        assert_eq!(uri_encode("/v1/a b", false), "/v1/a%20b");
+        assert_eq!(uri_encode("中", true), "%E4%B8%AD");
+    }
+
+    #[test]
+    fn canonical_uri_encodes_decoded_segments_once() {
+        assert_eq!(canonical_uri("/v1/a%20b/%E4%B8%AD"), "/v1/a%20b/%E4%B8%AD");
+        assert_eq!(canonical_uri("/v1/a:b"), "/v1/a%3Ab");
+        assert_eq!(canonical_uri("/v1/a%2Fb"), "/v1/a%2Fb");
+    }
+
+    #[test]
+    fn token_cache_is_keyed_by_access_key_and_iam_url() {
+        let other_url = BceTokenProvider { iam_url: "https://iam.example.com/v1/BCE-BEARER/token".to_string(), ..provider() };
+        let other_key = BceTokenProvider { access_key: "ak2".to_string(), ..provider() };
+        assert_ne!(provider().cache_path(), other_url.cache_path());
+        assert_ne!(provider().cache_path(), other_key.cache_path());
+        assert_eq!(provider().cache_path(), provider().cache_path());
+    }
+
+    #[test]
+    fn signs_canonical_request() {
+        let date = "2024-01-01T00:00:00Z";
+        let url = reqwest::Url::parse("https://iam.example.com/v1/BCE-BEARER/token?b=2&a=x%20y&authorization=skip").unwrap();
+        let authorization = provider().sign("GET", &url, &[("X-Bce-Date", date), ("Host", " iam.example.com ")], date);
+
+        let prefix = format!("bce-auth-v1/ak/{}/{}", date, SIGNATURE_EXPIRATION_SECS);
+        let signing_key = hmac_sha256_hex(b"sk", &prefix);
+        let canonical_request = "GET\n/v1/BCE-BEARER/token\na=x%20y&b=2\nhost:iam.example.com\nx-bce-date:2024-01-01T00%3A00%3A00Z";
+        let signature = hmac_sha256_hex(signing_key.as_bytes(), canonical_request);
+        assert_eq!(authorization, format!("{}/host;x-bce-date/{}", prefix, signature));
+    }
+
+    #[test]
+    fn bearer_token_takes_precedence() {
+        assert!(matches!(Auth::new("tok".to_string(), Some("ak"), Some("sk"), None), Auth::Bearer(token) if token == "tok"));
+        assert!(matches!(Auth::new(String::new(), Some("ak"), Some("sk"), None), Auth::AkSk(_)));
+        assert!(matches!(Auth::new(String::new(), Some(""), Some(""), None), Auth::None));
+    }
+}
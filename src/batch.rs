@@ -0,0 +1,361 @@
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Mutex, Semaphore};
+
+use crate::api::{self, Usage};
+use crate::cli::{Cli, EXIT_API_ERROR, EXIT_USAGE_ERROR};
+use crate::i18n;
+use crate::AVAILABLE_MODELS;
+
+// Help texts come from the locale catalog, see cli::parse
+#[derive(Args)]
+pub struct BatchArgs {
+    pub input: PathBuf,
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+    #[arg(short = 'j', long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub concurrency: u32,
+    #[arg(short, long, default_value_t = 0.0)]
+    pub rate: f64,
+}
+
+#[derive(Deserialize)]
+struct BatchRequest {
+    #[serde(default)]
+    id: Option<Value>,
+    prompt: String,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(default)]
+    params: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BatchResult {
+    id: String,
+    line: usize,
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    response: Option<String>,
+    #[serde(default)]
+    latency_ms: Option<u64>,
+    #[serde(default)]
+    usage: Option<Usage>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+struct Job {
+    id: String,
+    line: usize,
+    model: String,
+    payload: Value,
+}
+
+/// Runs `llm_tui batch` and returns the process exit code.
+pub async fn run_batch(cli: &Cli, args: &BatchArgs) -> i32 {
+    let (config, profile, auth) = match cli.load_credentials() {
+        Ok(loaded) => loaded,
+        Err(e) => return usage_error(&e),
+    };
+    if args.rate < 0.0 || !args.rate.is_finite() {
+        return usage_error(i18n::text().batch_rate_invalid);
+    }
+
+    let contents = match fs::read_to_string(&args.input) {
+        Ok(contents) => contents,
+        Err(e) => return usage_error(&(i18n::text().batch_input_error)(&args.input.display(), &e)),
+    };
+    let output = args.output.clone().unwrap_or_else(|| default_output_path(&args.input));
+    let (completed, recorded_invalid) = match recorded_results(&output) {
+        Ok(recorded) => recorded,
+        Err(e) => return usage_error(&e),
+    };
+
+    let default_model = cli.model(&profile);
+    let mut jobs = Vec::new();
+    let mut invalid = Vec::new();
+    let mut seen = HashSet::new();
+    let mut skipped = 0;
+    let text = i18n::text();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_no = index + 1;
+        match parse_job(line, line_no, &default_model, &profile.params) {
+            Ok(job) => {
+                if !seen.insert(job.id.clone()) {
+                    return usage_error(&(text.batch_line)(line_no, &(text.batch_duplicate_id)(&job.id)));
+                }
+                if completed.contains(&job.id) {
+                    skipped += 1;
+                } else {
+                    jobs.push(job);
+                }
+            }
+            // Already reported by an earlier run, there is nothing to retry
+            Err(_) if recorded_invalid.contains(&line_no) => skipped += 1,
+            Err(e) => {
+                invalid.push(BatchResult::failed(line_id(line_no), line_no, None, (text.batch_line)(line_no, &e)));
+            }
+        }
+    }
+
+    let total = jobs.len() + invalid.len();
+    if skipped == 0 {
+        eprintln!("{}", (text.batch_start)(total, &output.display()));
+    } else {
+        eprintln!("{}", (text.batch_resume)(skipped, total, &output.display()));
+    }
+
+    let mut file = match OpenOptions::new().create(true).append(true).open(&output) {
+        Ok(file) => file,
+        Err(e) => return usage_error(&(text.batch_output_error)(&output.display(), &e)),
+    };
+    // Terminate a partially written line left by an interrupted run
+    let truncated = fs::read(&output).is_ok_and(|bytes| bytes.last().is_some_and(|&b| b != b'\n'));
+    if truncated {
+        let _ = writeln!(file);
+    }
+
+    // A single writer appends and flushes each result so an interrupted run can be resumed
+    let (tx, mut rx) = mpsc::channel::<BatchResult>(64);
+    let writer = tokio::spawn(async move {
+        let mut done = 0;
+        let mut failed = 0;
+        while let Some(result) = rx.recv().await {
+            done += 1;
+            match &result.error {
+                Some(e) => {
+                    failed += 1;
+                    eprintln!("{}", (text.batch_failed)(done, total, &result.id, e));
+                }
+                None => eprintln!("{}", (text.batch_succeeded)(done, total, &result.id, result.latency_ms.unwrap_or_default())),
+            }
+            if let Ok(line) = serde_json::to_string(&result) {
+                let _ = writeln!(file, "{}", line).and_then(|_| file.flush());
+            }
+        }
+        failed
+    });
+
+    for result in invalid {
+        let _ = tx.send(result).await;
+    }
+
+    let client = api::build_client();
+    let api_url = Arc::new(cli.api_url(&profile));
+    let max_retries = config.max_retries;
+    let semaphore = Arc::new(Semaphore::new(args.concurrency as usize));
+    let limiter = (args.rate > 0.0).then(|| {
+        let mut interval = tokio::time::interval(Duration::from_secs_f64(1.0 / args.rate));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        Arc::new(Mutex::new(interval))
+    });
+
+    let mut handles = Vec::new();
+    for job in jobs {
+        let permit = match semaphore.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => break,
+        };
+        if let Some(limiter) = &limiter {
+            limiter.lock().await.tick().await;
+        }
+        let client = client.clone();
+        let api_url = api_url.clone();
+        let auth = auth.clone();
+        let tx = tx.clone();
+        handles.push(tokio::spawn(async move {
+            let started = Instant::now();
+            let id = job.id.clone();
+            let on_retry = |retry: &api::RetryAttempt| {
+                eprintln!("{} {}", id, (text.cli_retrying)(&retry.error, retry.delay.as_secs_f64(), retry.attempt, retry.max_attempts));
+            };
+            let reply = match auth.bearer_token(&client).await {
+                Ok(auth_token) => api::send_chat_as(&client, &api_url, &auth, &auth_token, &job.payload, max_retries, on_retry).await,
+                Err(e) => Err(e),
+            };
+            let result = match reply {
+                Ok(reply) => BatchResult {
+                    id: job.id,
+                    line: job.line,
+                    model: Some(job.model),
+                    response: Some(reply.content),
+                    latency_ms: Some(reply.latency.as_millis() as u64),
+                    usage: reply.usage,
+                    error: None,
+                },
+                Err(e) => {
+                    let mut result = BatchResult::failed(job.id, job.line, Some(job.model), format!("{:#}", e));
+                    result.latency_ms = Some(started.elapsed().as_millis() as u64);
+                    result
+                }
+            };
+            let _ = tx.send(result).await;
+            drop(permit);
+        }));
+    }
+    for handle in handles {
+        let _ = handle.await;
+    }
+    drop(tx);
+
+    let failed = writer.await.unwrap_or_default();
+    eprintln!("{}", (text.batch_done)(total - failed, failed));
+    if failed > 0 { EXIT_API_ERROR } else { 0 }
+}
+
+impl BatchResult {
+    fn failed(id: String, line: usize, model: Option<String>, error: String) -> Self {
+        BatchResult {
+            id,
+            line,
+            model,
+            response: None,
+            latency_ms: None,
+            usage: None,
+            error: Some(error),
+        }
+    }
+}
+
+// Request params override the profile's params
+fn parse_job(line: &str, line_no: usize, default_model: &str, default_params: &Map<String, Value>) -> Result<Job, String> {
+    let request: BatchRequest = serde_json::from_str(line)
+        .map_err(|e| (i18n::text().batch_invalid_request)(&e))?;
+    let id = match request.id {
+        Some(Value::String(id)) if id.starts_with(LINE_ID_PREFIX) => {
+            return Err((i18n::text().batch_reserved_id)(LINE_ID_PREFIX));
+        }
+        Some(Value::String(id)) => id,
+        Some(Value::Number(id)) => id.to_string(),
+        Some(_) => return Err(i18n::text().batch_bad_id.to_string()),
+        None => line_id(line_no),
+    };
+    let model = request.model.unwrap_or_else(|| default_model.to_string());
+    if !AVAILABLE_MODELS.contains(&model.as_str()) {
+        return Err((i18n::text().batch_unknown_model)(&model));
+    }
+
+    let mut messages = Vec::new();
+    if let Some(system) = request.system {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    messages.push(serde_json::json!({ "role": "user", "content": request.prompt }));
+    let mut payload = api::build_payload(&model, messages);
+    api::apply_params(&mut payload, default_params);
+    api::apply_params(&mut payload, &request.params);
+
+    Ok(Job { id, line: line_no, model, payload })
+}
+
+fn default_output_path(input: &Path) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("batch");
+    input.with_file_name(format!("{}.results.jsonl", stem))
+}
+
+// Requests without an id, and lines that are not valid requests, are named by
+// line number in their own namespace so they can't collide with given ids
+const LINE_ID_PREFIX: &str = "line:";
+
+fn line_id(line_no: usize) -> String {
+    format!("{}{}", LINE_ID_PREFIX, line_no)
+}
+
+// Ids that already have a successful result in the output file, and the
+// numbers of the lines recorded as invalid requests
+fn recorded_results(output: &Path) -> Result<(HashSet<String>, HashSet<usize>), String> {
+    let mut completed = HashSet::new();
+    let mut invalid = HashSet::new();
+    let contents = match fs::read_to_string(output) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((completed, invalid)),
+        Err(e) => return Err((i18n::text().batch_results_error)(&output.display(), &e)),
+    };
+    for line in contents.lines() {
+        // A partially written last line from an interrupted run is ignored
+        let Ok(result) = serde_json::from_str::<BatchResult>(line) else {
+            continue;
+        };
+        match (&result.error, &result.model) {
+            (None, _) => {
+                completed.insert(result.id);
+            }
+            // Only invalid lines are recorded without a model
+            (Some(_), None) => {
+                invalid.insert(result.line);
+            }
+            (Some(_), Some(_)) => {}
+        }
+    }
+    Ok((completed, invalid))
+}
+
+fn usage_error(message: &str) -> i32 {
+    eprintln!("{}", (i18n::text().error)(message));
+    EXIT_USAGE_ERROR
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(line: &str, line_no: usize) -> Result<Job, String> {
+        parse_job(line, line_no, "ernie-4.0-8k", &Map::new())
+    }
+
+    #[test]
+    fn ids_default_to_line_namespace() {
+        assert_eq!(job(r#"{"prompt": "hi"}"#, 3).unwrap().id, "line:3");
+        assert_eq!(job(r#"{"id": 3, "prompt": "hi"}"#, 1).unwrap().id, "3");
+        assert_eq!(job(r#"{"id": "q1", "prompt": "hi"}"#, 1).unwrap().id, "q1");
+        assert!(job(r#"{"id": "line:3", "prompt": "hi"}"#, 1).is_err());
+        assert!(job(r#"{"id": [1], "prompt": "hi"}"#, 1).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        assert!(job("not json", 1).is_err());
+        assert!(job(r#"{"id": "q1"}"#, 1).is_err());
+        assert!(job(r#"{"prompt": "hi", "model": "gpt-4"}"#, 1).is_err());
+    }
+
+    #[test]
+    fn request_params_override_profile_params() {
+        let defaults = Map::from_iter([("temperature".to_string(), Value::from(0.9)), ("top_p".to_string(), Value::from(0.5))]);
+        let line = r#"{"prompt": "hi", "system": "sys", "params": {"temperature": 0.2}}"#;
+        let job = parse_job(line, 1, "ernie-4.0-8k", &defaults).unwrap();
+        assert_eq!(job.payload["temperature"], 0.2);
+        assert_eq!(job.payload["top_p"], 0.5);
+        assert_eq!(job.payload["messages"][0]["role"], "system");
+        assert_eq!(job.payload["messages"][1]["content"], "hi");
+    }
+
+    #[test]
+    fn recorded_results_separate_successes_and_invalid_lines() {
+        let path = std::env::temp_dir().join(format!("llm_tui_batch_test_{}.jsonl", std::process::id()));
+        let results = [
+            r#"{"id": "q1", "line": 1, "model": "ernie-4.0-8k", "response": "ok"}"#,
+            r#"{"id": "q2", "line": 2, "model": "ernie-4.0-8k", "error": "HTTP 500"}"#,
+            r#"{"id": "line:3", "line": 3, "error": "第 3 行: 无效的请求"}"#,
+            r#"{"id": "q4", "line": 4, "mod"#,
+        ];
+        fs::write(&path, results.join("\n")).unwrap();
+        let (completed, invalid) = recorded_results(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(completed, HashSet::from(["q1".to_string()]));
+        assert_eq!(invalid, HashSet::from([3]));
+    }
+}
@@ -0,0 +1,272 @@
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use serde_json::Value;
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+
+use crate::api;
+use crate::auth::Auth;
+use crate::batch::BatchArgs;
+use crate::config;
+use crate::credentials::{self, TokenCommand};
+use crate::i18n;
+use crate::session;
+use crate::paths;
+use crate::{Config, Profile, AVAILABLE_MODELS, DEFAULT_PROFILE};
+
+pub const EXIT_API_ERROR: i32 = 1;
+pub const EXIT_USAGE_ERROR: i32 = 2;
+
+// Help texts come from the locale catalog, see `parse`
+#[derive(Parser)]
+#[command(name = "llm_tui", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    pub prompt: Option<String>,
+    #[arg(short, long, global = true, value_parser = parse_model)]
+    pub model: Option<String>,
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    #[arg(short, long, global = true, value_name = "URL", value_parser = parse_endpoint)]
+    pub endpoint: Option<String>,
+    #[arg(short, long, global = true, value_name = "NAME")]
+    pub profile: Option<String>,
+    #[arg(long, value_name = "NAME", value_parser = parse_session)]
+    pub session: Option<String>,
+}
+
+/// Parses the command line, with help texts in the language of `LANG`
+/// since the config that may choose another one isn't loaded yet.
+pub fn parse() -> Cli {
+    let text = i18n::text();
+    let command = Cli::command()
+        .about(text.cli_about)
+        .mut_arg("prompt", |arg| arg.help(text.cli_prompt))
+        .mut_arg("model", |arg| arg.help(text.cli_model))
+        .mut_arg("config", |arg| arg.help(text.cli_config))
+        .mut_arg("endpoint", |arg| arg.help(text.cli_endpoint))
+        .mut_arg("profile", |arg| arg.help(text.cli_profile))
+        .mut_arg("session", |arg| arg.help(text.cli_session))
+        .mut_subcommand("ask", |ask| ask
+            .about(text.cli_ask)
+            .mut_arg("prompt", |arg| arg.help(text.cli_ask_prompt))
+            .mut_arg("stdin", |arg| arg.help(text.cli_ask_stdin))
+            .mut_arg("system", |arg| arg.help(text.cli_ask_system))
+            .mut_arg("json", |arg| arg.help(text.cli_ask_json))
+            .mut_arg("curl", |arg| arg.help(text.cli_ask_curl)))
+        .mut_subcommand("batch", |batch| batch
+            .about(text.cli_batch)
+            .mut_arg("input", |arg| arg.help(text.cli_batch_input))
+            .mut_arg("output", |arg| arg.help(text.cli_batch_output))
+            .mut_arg("concurrency", |arg| arg.help(text.cli_batch_concurrency))
+            .mut_arg("rate", |arg| arg.help(text.cli_batch_rate)))
+        .mut_subcommand("token", |token| token
+            .about(text.cli_token)
+            .mut_subcommand("encrypt", |encrypt| encrypt.about(text.cli_token_encrypt))
+            .mut_subcommand("decrypt", |decrypt| decrypt.about(text.cli_token_decrypt)));
+    Cli::from_arg_matches(&command.get_matches()).unwrap_or_else(|e| e.exit())
+}
+
+impl Cli {
+    pub fn load_config(&self) -> Result<Config, String> {
+        let config = self.read_config()?;
+        i18n::init(config.language.as_deref()).map_err(|e| format!("{:#}", e))?;
+        Ok(config)
+    }
+
+    fn read_config(&self) -> Result<Config, String> {
+        match &self.config {
+            Some(path) => {
+                if !path.exists() {
+                    return Err((i18n::text().config_not_found)(&path.display()));
+                }
+                Config::load(path.clone()).map_err(|e| format!("{:#}", e))
+            }
+            None => {
+                match paths::migrate_legacy() {
+                    Ok(Some(legacy)) => eprintln!("{}", (i18n::text().legacy_migrated)(&legacy.display(), &paths::config_dir().display())),
+                    Ok(None) => {}
+                    // The old files stay where they are; starting with a new config beats not starting
+                    Err(e) => eprintln!("{}", (i18n::text().legacy_migrate_failed)(&format!("{:#}", e))),
+                }
+                Config::load(paths::config_file()).map_err(|e| format!("{:#}", e))
+            }
+        }
+    }
+
+    /// The profile to use: --profile, else the one last picked in the UI,
+    /// else `active_profile` from the config.
+    pub fn profile_name(&self, config: &Config) -> Result<String, String> {
+        let name = self.profile.clone()
+            .or_else(|| config::load_active_profile(&config.path).filter(|name| config.profile(name).is_some()))
+            .or_else(|| config.active_profile.clone())
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+        let Some(profile) = config.profile(&name) else {
+            return Err((i18n::text().profile_not_found)(&name, &config.profile_names().join(", ")));
+        };
+        if let Some(model) = &profile.model {
+            parse_model(model).map_err(|e| (i18n::text().profile_invalid_model)(&name, model, &e))?;
+        }
+        Ok(name)
+    }
+
+    /// Loads the config and resolves how requests are authorized, failing if
+    /// neither a token nor AK/SK is configured.
+    pub fn load_credentials(&self) -> Result<(Config, Profile, Auth), String> {
+        let config = self.load_config()?;
+        let name = self.profile_name(&config)?;
+        let profile = config.profile(&name).cloned().unwrap_or_default();
+        let credentials = credentials::resolve(&profile).map_err(|e| format!("{:#}", e))?;
+        let auth = profile.auth(credentials.token, credentials.secret_key.as_deref());
+        if !auth.is_configured() {
+            return Err(i18n::text().cli_no_auth.to_string());
+        }
+        Ok((config, profile, auth))
+    }
+
+    pub fn api_url(&self, profile: &Profile) -> String {
+        self.endpoint.clone()
+            .or_else(|| profile.endpoint.clone())
+            .unwrap_or_else(|| api::DEFAULT_API_URL.to_string())
+    }
+
+    pub fn model(&self, profile: &Profile) -> String {
+        self.model.clone()
+            .or_else(|| profile.model.clone())
+            .unwrap_or_else(|| crate::DEFAULT_MODEL.to_string())
+    }
+}
+
+fn parse_model(model: &str) -> Result<String, String> {
+    if AVAILABLE_MODELS.contains(&model) {
+        Ok(model.to_string())
+    } else {
+        Err((i18n::text().unknown_model)(&AVAILABLE_MODELS.join(", ")))
+    }
+}
+
+fn parse_endpoint(url: &str) -> Result<String, String> {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(url.to_string())
+    } else {
+        Err(i18n::text().endpoint_invalid.to_string())
+    }
+}
+
+fn parse_session(name: &str) -> Result<String, String> {
+    session::validate_name(name).map_err(|e| e.to_string())?;
+    Ok(name.to_string())
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    Ask(AskArgs),
+    Batch(BatchArgs),
+    Token {
+        #[command(subcommand)]
+        command: TokenCommand,
+    },
+}
+
+#[derive(Args)]
+pub struct AskArgs {
+    pub prompt: Option<String>,
+    #[arg(long)]
+    pub stdin: bool,
+    #[arg(short, long)]
+    pub system: Option<String>,
+    #[arg(long)]
+    pub json: bool,
+    #[arg(long)]
+    pub curl: bool,
+}
+
+/// Runs `llm_tui ask` and returns the process exit code.
+pub async fn run_ask(cli: &Cli, args: &AskArgs) -> i32 {
+    let prompt = match read_prompt(args.prompt.as_deref(), args.stdin) {
+        Ok(prompt) => prompt,
+        Err(e) => return fail(args, EXIT_USAGE_ERROR, &e),
+    };
+    if prompt.trim().is_empty() {
+        return fail(args, EXIT_USAGE_ERROR, i18n::text().cli_prompt_empty);
+    }
+
+    let (config, profile, auth) = match cli.load_credentials() {
+        Ok(loaded) => loaded,
+        Err(e) => return fail(args, EXIT_USAGE_ERROR, &e),
+    };
+    let model = cli.model(&profile);
+    let api_url = cli.api_url(&profile);
+
+    let mut messages = Vec::new();
+    if let Some(system) = &args.system {
+        messages.push(serde_json::json!({ "role": "system", "content": system }));
+    }
+    messages.push(serde_json::json!({ "role": "user", "content": prompt }));
+    let mut payload = api::build_payload(&model, messages);
+    api::apply_params(&mut payload, &profile.params);
+
+    if args.curl {
+        println!("{}", api::format_curl_command(&api_url, &payload));
+        return 0;
+    }
+
+    let client = api::build_client();
+    let auth_token = match auth.bearer_token(&client).await {
+        Ok(token) => token,
+        Err(e) => return fail(args, EXIT_API_ERROR, &format!("{:#}", e)),
+    };
+
+    let on_retry = |retry: &api::RetryAttempt| {
+        eprintln!("{}", (i18n::text().cli_retrying)(&retry.error, retry.delay.as_secs_f64(), retry.attempt, retry.max_attempts));
+    };
+    match api::send_chat_as(&client, &api_url, &auth, &auth_token, &payload, config.max_retries, on_retry).await {
+        Ok(reply) => {
+            if args.json {
+                let output = serde_json::json!({
+                    "model": model,
+                    "content": reply.content,
+                    "usage": reply.usage,
+                    "latency_ms": reply.latency.as_millis() as u64,
+                });
+                println!("{}", output);
+            } else {
+                println!("{}", reply.content);
+            }
+            0
+        }
+        Err(e) => fail(args, EXIT_API_ERROR, &e.to_string()),
+    }
+}
+
+// Standard input is only read when asked for, so a prompt given as an argument
+// never waits on an inherited stdin (cron, CI, systemd)
+fn read_prompt(prompt: Option<&str>, append_stdin: bool) -> Result<String, String> {
+    let prompt = prompt.filter(|prompt| *prompt != "-");
+    if let (Some(prompt), false) = (prompt, append_stdin) {
+        return Ok(prompt.to_string());
+    }
+    let stdin = io::stdin();
+    if stdin.is_terminal() {
+        return prompt.map(str::to_string)
+            .ok_or_else(|| i18n::text().cli_prompt_missing.to_string());
+    }
+
+    let mut piped = String::new();
+    stdin.lock().read_to_string(&mut piped)
+        .map_err(|e| (i18n::text().cli_stdin_error)(&e))?;
+    Ok(match prompt {
+        Some(prompt) if !piped.trim().is_empty() => format!("{}\n\n{}", prompt, piped.trim_end()),
+        Some(prompt) => prompt.to_string(),
+        None => piped,
+    })
+}
+
+fn fail(args: &AskArgs, code: i32, message: &str) -> i32 {
+    if args.json {
+        let output: Value = serde_json::json!({ "error": message, "exit_code": code });
+        println!("{}", output);
+    }
+    eprintln!("{}", (i18n::text().error)(message));
+    code
+}
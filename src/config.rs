@@ -0,0 +1,360 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, TableLike};
+
+use crate::credentials;
+use crate::i18n;
+use crate::paths;
+
+/// Schema version written to the `version` field of the config file.
+pub const CONFIG_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Json,
+    Toml,
+}
+
+impl Format {
+    /// TOML for `.toml` files, JSON for anything else.
+    pub fn of(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+}
+
+/// Parses a config file, upgrading older schema versions. Returns the config
+/// and whether it was migrated and should be written back.
+pub fn parse<T: DeserializeOwned>(contents: &str, format: Format) -> Result<(T, bool)> {
+    let mut value = parse_value(contents, format)?;
+    let migrated = migrate(&mut value)?;
+    let config = serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        if path == "." {
+            anyhow!("{}", e.into_inner())
+        } else {
+            anyhow!((i18n::text().config_field)(&path, &e.into_inner()))
+        }
+    })?;
+    Ok((config, migrated))
+}
+
+fn parse_value(contents: &str, format: Format) -> Result<Value> {
+    Ok(match format {
+        Format::Json => serde_json::from_str(contents).map_err(|e| anyhow!((i18n::text().config_json_syntax)(&e)))?,
+        Format::Toml => toml::from_str(contents).map_err(|e| anyhow!((i18n::text().config_toml_syntax)(&e)))?,
+    })
+}
+
+// Brings an older config up to CONFIG_VERSION in place
+fn migrate(value: &mut Value) -> Result<bool> {
+    let object = value.as_object_mut().ok_or_else(|| anyhow!(i18n::text().config_not_object))?;
+    let version = match object.get("version") {
+        None => 0,
+        Some(version) => version.as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow!(i18n::text().config_bad_version))?,
+    };
+    if version > CONFIG_VERSION {
+        bail!((i18n::text().config_too_new)(version.into(), CONFIG_VERSION.into()));
+    }
+    if version == CONFIG_VERSION {
+        return Ok(false);
+    }
+
+    // Version 0 is the unversioned `{"auth_token": ...}` file. Its fields
+    // carry over unchanged; older releases wrote an empty token when none was set.
+    if object.get("auth_token").and_then(Value::as_str) == Some("") {
+        object.remove("auth_token");
+    }
+    object.insert("version".to_string(), Value::from(CONFIG_VERSION));
+    Ok(true)
+}
+
+/// Writes the config if it changed, first copying the current file to `<name>.bak`.
+/// An existing file only has the changed keys rewritten, keeping its comments,
+/// key order and the defaults it leaves unset.
+pub fn write<T: Serialize + DeserializeOwned>(path: &Path, config: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let format = Format::of(path);
+    let previous = match fs::read_to_string(path) {
+        Ok(previous) => Some(previous),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let contents = match (&previous, format) {
+        (Some(previous), _) => update(previous, format, config)?,
+        (None, Format::Json) => serde_json::to_string_pretty(config)?,
+        (None, Format::Toml) => toml::to_string_pretty(config).context(i18n::text().config_toml_error)?,
+    };
+    if let Some(previous) = previous {
+        if previous == contents {
+            return Ok(());
+        }
+        credentials::write_private(&backup_path(path), previous.as_bytes())
+            .with_context(|| (i18n::text().config_backup_error)(&path.display()))?;
+    }
+    credentials::write_private(path, contents.as_bytes())?;
+    Ok(())
+}
+
+// Applies to the file's own document what migrating it changed, then what
+// differs between the config as read from it and `config`
+fn update<T: Serialize + DeserializeOwned>(previous: &str, format: Format, config: &T) -> Result<String> {
+    let raw = parse_value(previous, format)?;
+    let mut migrated = raw.clone();
+    migrate(&mut migrated)?;
+    let read: T = serde_json::from_value(migrated.clone())?;
+    let (read, new) = (serde_json::to_value(read)?, serde_json::to_value(config)?);
+    let empty = Map::new();
+    let object = |value: &Value| value.as_object().unwrap_or(&empty).clone();
+    match format {
+        Format::Json => {
+            let mut document = object(&raw);
+            apply_json(&mut document, &object(&raw), &object(&migrated));
+            apply_json(&mut document, &object(&read), &object(&new));
+            Ok(serde_json::to_string_pretty(&document)?)
+        }
+        Format::Toml => {
+            let mut document: DocumentMut = previous.parse().map_err(|e| anyhow!((i18n::text().config_toml_syntax)(&e)))?;
+            apply_toml(document.as_table_mut(), &object(&raw), &object(&migrated));
+            apply_toml(document.as_table_mut(), &object(&read), &object(&new));
+            Ok(document.to_string())
+        }
+    }
+}
+
+fn apply_json(document: &mut Map<String, Value>, old: &Map<String, Value>, new: &Map<String, Value>) {
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        document.shift_remove(key);
+    }
+    for (key, value) in new {
+        if old.get(key) == Some(value) {
+            continue;
+        }
+        let empty = Map::new();
+        let old_child = old.get(key).and_then(Value::as_object).unwrap_or(&empty);
+        match (document.get_mut(key), value) {
+            (Some(Value::Object(child)), Value::Object(new_child)) => apply_json(child, old_child, new_child),
+            (_, Value::Null) => {
+                document.shift_remove(key);
+            }
+            _ => {
+                document.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn apply_toml(document: &mut dyn TableLike, old: &Map<String, Value>, new: &Map<String, Value>) {
+    for key in old.keys().filter(|key| !new.contains_key(*key)) {
+        document.remove(key);
+    }
+    for (key, value) in new {
+        if old.get(key) == Some(value) {
+            continue;
+        }
+        let empty = Map::new();
+        let old_child = old.get(key).and_then(Value::as_object).unwrap_or(&empty);
+        match (document.get_mut(key).and_then(Item::as_table_like_mut), value) {
+            (Some(child), Value::Object(new_child)) => apply_toml(child, old_child, new_child),
+            // TOML has no null; unset options are left out
+            _ => match value.serialize(toml_edit::ser::ValueSerializer::new()) {
+                Ok(value) => {
+                    document.insert(key, Item::Value(value));
+                }
+                Err(_) => {
+                    document.remove(key);
+                }
+            },
+        }
+    }
+}
+
+/// Writes a config that `parse` migrated. The file as it was before the
+/// upgrade is kept as `<name>.pre-v<version>.bak`, which later writes leave alone.
+pub fn write_migrated<T: Serialize + DeserializeOwned>(path: &Path, config: &T) -> Result<()> {
+    let backup = migration_backup_path(path);
+    if !backup.exists() {
+        if let Ok(previous) = fs::read(path) {
+            credentials::write_private(&backup, &previous)
+                .with_context(|| (i18n::text().config_backup_error)(&path.display()))?;
+        }
+    }
+    write(path, config)
+}
+
+fn active_profiles_path() -> PathBuf {
+    paths::state_dir().join("profiles.json")
+}
+
+// Profiles picked in the switcher, by config file
+fn load_active_profiles() -> BTreeMap<String, String> {
+    fs::read_to_string(active_profiles_path()).ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn state_key(config_path: &Path) -> String {
+    fs::canonicalize(config_path).unwrap_or_else(|_| config_path.to_path_buf()).display().to_string()
+}
+
+/// The profile last picked in the switcher for this config file.
+pub fn load_active_profile(config_path: &Path) -> Option<String> {
+    load_active_profiles().remove(&state_key(config_path))
+}
+
+/// Remembers the picked profile in the state dir, like the layout, so that
+/// switching profiles doesn't rewrite the config file.
+pub fn save_active_profile(config_path: &Path, name: &str) -> Result<()> {
+    let mut profiles = load_active_profiles();
+    profiles.insert(state_key(config_path), name.to_string());
+    let path = active_profiles_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&profiles)?)?;
+    Ok(())
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+pub fn migration_backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, &format!(".pre-v{}.bak", CONFIG_VERSION))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Serialize)]
+    struct Sample {
+        version: u32,
+        auth_token: Option<String>,
+        #[serde(default)]
+        retries: u32,
+    }
+
+    #[test]
+    fn migrates_unversioned_config() {
+        let (config, migrated) = parse::<Sample>(r#"{"auth_token": "tok"}"#, Format::Json).unwrap();
+        assert!(migrated);
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.auth_token.as_deref(), Some("tok"));
+    }
+
+    #[test]
+    fn drops_empty_legacy_token() {
+        let (config, migrated) = parse::<Sample>(r#"{"auth_token": ""}"#, Format::Json).unwrap();
+        assert!(migrated);
+        assert_eq!(config.auth_token, None);
+    }
+
+    #[test]
+    fn leaves_current_version_alone() {
+        let contents = format!("version = {}\nauth_token = \"\"\n", CONFIG_VERSION);
+        let (config, migrated) = parse::<Sample>(&contents, Format::Toml).unwrap();
+        assert!(!migrated);
+        assert_eq!(config.auth_token.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn rejects_newer_and_invalid_versions() {
+        let newer = format!(r#"{{"version": {}}}"#, CONFIG_VERSION + 1);
+        assert!(parse::<Sample>(&newer, Format::Json).is_err());
+        assert!(parse::<Sample>(r#"{"version": -1}"#, Format::Json).is_err());
+        assert!(parse::<Sample>(r#"{"version": "1"}"#, Format::Json).is_err());
+        assert!(parse::<Sample>("[]", Format::Json).is_err());
+    }
+
+    #[test]
+    fn format_follows_extension() {
+        assert!(Format::of(Path::new("config.TOML")) == Format::Toml);
+        assert!(Format::of(Path::new("config.json")) == Format::Json);
+        assert!(Format::of(Path::new("config")) == Format::Json);
+        assert_eq!(backup_path(Path::new("/tmp/config.toml")), Path::new("/tmp/config.toml.bak"));
+    }
+
+    #[test]
+    fn write_backs_up_previous_file() {
+        let dir = std::env::temp_dir().join(format!("llm_tui_config_test_{}", std::process::id()));
+        let path = dir.join("config.toml");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, "# old\n").unwrap();
+        write(&path, &Sample { version: CONFIG_VERSION, auth_token: Some("tok".to_string()), retries: 0 }).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        let backup = fs::read_to_string(backup_path(&path)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(backup, "# old\n");
+        let (config, migrated) = parse::<Sample>(&written, Format::Toml).unwrap();
+        assert!(!migrated);
+        assert_eq!(config.auth_token.as_deref(), Some("tok"));
+    }
+
+    #[test]
+    fn update_rewrites_only_changed_keys() {
+        let toml = "# comment\nversion = 1\nother = \"kept\"  # why\nauth_token = \"old\"\n";
+        let (mut config, _) = parse::<Sample>(toml, Format::Toml).unwrap();
+        config.auth_token = Some("new".to_string());
+        assert_eq!(
+            update(toml, Format::Toml, &config).unwrap(),
+            "# comment\nversion = 1\nother = \"kept\"  # why\nauth_token = \"new\"\n"
+        );
+
+        // Key order survives, unset options are removed and defaults stay unset
+        let json = r#"{"other": 1, "auth_token": "old", "version": 1}"#;
+        let (mut config, _) = parse::<Sample>(json, Format::Json).unwrap();
+        config.auth_token = None;
+        let written: Value = serde_json::from_str(&update(json, Format::Json, &config).unwrap()).unwrap();
+        let keys: Vec<&String> = written.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["other", "version"]);
+    }
+
+    #[test]
+    fn update_applies_the_migration() {
+        let toml = "# comment\nother = 1\nauth_token = \"\"\n";
+        let (config, migrated) = parse::<Sample>(toml, Format::Toml).unwrap();
+        assert!(migrated);
+        assert_eq!(update(toml, Format::Toml, &config).unwrap(), format!("# comment\nother = 1\nversion = {}\n", CONFIG_VERSION));
+    }
+
+    #[test]
+    fn migration_backup_survives_later_writes() {
+        let dir = std::env::temp_dir().join(format!("llm_tui_migration_test_{}", std::process::id()));
+        let path = dir.join("config.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{"auth_token": "old"}"#).unwrap();
+        let (mut config, migrated) = parse::<Sample>(&fs::read_to_string(&path).unwrap(), Format::Json).unwrap();
+        assert!(migrated);
+        write_migrated(&path, &config).unwrap();
+        config.auth_token = Some("new".to_string());
+        write(&path, &config).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        write(&path, &config).unwrap();
+        let backup = fs::read_to_string(backup_path(&path)).unwrap();
+        let migration_backup = fs::read_to_string(migration_backup_path(&path)).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(migration_backup, r#"{"auth_token": "old"}"#);
+        // The unchanged second write did not replace the backup with the current file
+        assert_ne!(backup, written);
+    }
+}
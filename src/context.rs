@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// What to do with older turns once the conversation no longer fits the
+/// model's context window.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextStrategy {
+    /// Stop sending the oldest turns
+    #[default]
+    Truncate,
+    /// Ask the model to summarize the oldest turns into a pinned summary
+    Summarize,
+}
+
+// Models without a size suffix in their name
+const KNOWN_CONTEXT_LIMITS: [(&str, u64); 2] = [
+    ("deepseek-v3", 65536),
+    ("deepseek-r1", 65536),
+];
+
+const DEFAULT_CONTEXT_LIMIT: u64 = 8192;
+
+/// Context window of `model`, taken from the size suffix in its name
+/// (e.g. `ernie-4.0-8k` → 8192, `ernie-speed-128k` → 131072).
+pub fn context_limit(model: &str) -> u64 {
+    if let Some((_, limit)) = KNOWN_CONTEXT_LIMITS.iter().find(|(name, _)| *name == model) {
+        return *limit;
+    }
+    model.split('-')
+        .filter_map(|part| part.strip_suffix('k')?.parse::<u64>().ok())
+        .next()
+        .map(|k| k * 1024)
+        .unwrap_or(DEFAULT_CONTEXT_LIMIT)
+}
+
+/// Tokens kept free for the model's reply.
+pub fn reply_reserve(limit: u64) -> u64 {
+    (limit / 4).min(4096)
+}
+
+/// Tokens the request itself may use in a context window of `limit`.
+pub fn request_budget(limit: u64) -> u64 {
+    limit - reply_reserve(limit)
+}
+
+/// How many of the oldest turns to stop sending so that a request estimated
+/// at `tokens` fits `budget`. `turns` are the role and estimate of each turn,
+/// oldest first. The newest turn is always kept, and what is kept starts with
+/// a user turn.
+pub fn turns_to_drop(turns: &[(&str, u64)], tokens: u64, budget: u64) -> usize {
+    if tokens <= budget {
+        return 0;
+    }
+    let mut tokens = tokens;
+    let mut dropped = 0;
+    while dropped + 1 < turns.len() && (tokens > budget || turns[dropped].0 == "assistant") {
+        tokens = tokens.saturating_sub(turns[dropped].1 + MESSAGE_OVERHEAD);
+        dropped += 1;
+    }
+    dropped
+}
+
+/// Builds the system message from the configured system prompt and the
+/// summary of truncated turns, if any.
+pub fn system_message(system_prompt: Option<&str>, summary: Option<&str>) -> Option<Value> {
+    let content = match (system_prompt, summary) {
+        (Some(prompt), Some(summary)) => format!("{}\n\n{}{}", prompt, SUMMARY_PREFIX, summary),
+        (Some(prompt), None) => prompt.to_string(),
+        (None, Some(summary)) => format!("{}{}", SUMMARY_PREFIX, summary),
+        (None, None) => return None,
+    };
+    Some(serde_json::json!({ "role": "system", "content": content }))
+}
+
+// Text sent to the model stays the same whatever the UI language
+const SUMMARY_PREFIX: &str = "以下是之前对话的摘要:\n";
+
+const SUMMARIZE_INSTRUCTION: &str = "请将以下对话压缩为简洁的摘要，保留关键事实、结论、用户偏好和尚未解决的问题。只输出摘要本身。";
+
+/// The dropped turns, as role and content, written out for the summarize
+/// request after the summary they extend.
+pub fn summary_transcript(previous_summary: Option<&str>, turns: &[(&str, &str)]) -> String {
+    let mut transcript = String::new();
+    if let Some(summary) = previous_summary {
+        let _ = writeln!(transcript, "{}\n{}\n", PREVIOUS_SUMMARY_LABEL, summary);
+    }
+    for (role, content) in turns {
+        let role = if *role == "user" { USER_LABEL } else { ASSISTANT_LABEL };
+        let _ = writeln!(transcript, "{}: {}\n", role, content);
+    }
+    transcript
+}
+
+const PREVIOUS_SUMMARY_LABEL: &str = "之前的摘要:";
+const USER_LABEL: &str = "用户";
+const ASSISTANT_LABEL: &str = "AI";
+
+/// Request messages asking the model to summarize `transcript`, keeping the
+/// most recent part if the transcript itself does not fit in `budget`.
+pub fn summarize_messages(transcript: &str, budget: u64) -> Vec<Value> {
+    let mut text = transcript;
+    while estimate_tokens(text) > budget {
+        // Drop the oldest tenth of the transcript until it fits
+        let cut = text.char_indices().nth(text.chars().count() / 10 + 1).map_or(text.len(), |(i, _)| i);
+        text = &text[cut..];
+    }
+    vec![
+        serde_json::json!({ "role": "system", "content": SUMMARIZE_INSTRUCTION }),
+        serde_json::json!({ "role": "user", "content": text }),
+    ]
+}
+
+/// Rough local token estimate: one token per CJK character and about four
+/// characters per token for other text.
+pub fn estimate_tokens(text: &str) -> u64 {
+    let mut cjk: u64 = 0;
+    let mut other: u64 = 0;
+    for c in text.chars() {
+        if is_cjk(c) {
+            cjk += 1;
+        } else {
+            other += 1;
+        }
+    }
+    cjk + (other + 3) / 4
+}
+
+// Per-message overhead for role markers
+pub const MESSAGE_OVERHEAD: u64 = 4;
+// Rough cost of an image part; the real cost depends on the model and image size
+const IMAGE_TOKENS: u64 = 1000;
+
+pub fn estimate_messages(messages: &[Value]) -> u64 {
+    messages.iter()
+        .map(|msg| estimate_content(&msg["content"]) + MESSAGE_OVERHEAD)
+        .sum()
+}
+
+/// Estimate for a message `content`, either text or an array of parts.
+pub fn estimate_content(content: &Value) -> u64 {
+    match content {
+        Value::String(text) => estimate_tokens(text),
+        Value::Array(parts) => parts.iter()
+            .map(|part| match part["type"].as_str() {
+                Some("image_url") => IMAGE_TOKENS,
+                _ => estimate_tokens(part["text"].as_str().unwrap_or_default()),
+            })
+            .sum(),
+        _ => 0,
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x303F |   // CJK punctuation
+        0x3040..=0x30FF |   // Hiragana, Katakana
+        0x3400..=0x4DBF |   // CJK Extension A
+        0x4E00..=0x9FFF |   // CJK Unified Ideographs
+        0xAC00..=0xD7AF |   // Hangul
+        0xF900..=0xFAFF |   // CJK Compatibility Ideographs
+        0xFF00..=0xFFEF)    // Fullwidth forms
+}
+
+pub fn format_tokens(tokens: u64) -> String {
+    if tokens >= 1024 && tokens % 1024 == 0 {
+        format!("{}k", tokens / 1024)
+    } else if tokens >= 1000 {
+        format!("{:.1}k", tokens as f64 / 1000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn estimates_cjk_and_latin_text() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("你好，世界"), 5);
+        assert_eq!(estimate_tokens("你好 abc"), 3);
+    }
+
+    #[test]
+    fn estimates_messages_and_parts() {
+        let messages = [
+            json!({ "role": "user", "content": "abcd" }),
+            json!({ "role": "user", "content": [
+                { "type": "text", "text": "你好" },
+                { "type": "image_url", "image_url": { "url": "" } },
+            ] }),
+        ];
+        assert_eq!(estimate_messages(&messages), 1 + 2 + IMAGE_TOKENS + 2 * MESSAGE_OVERHEAD);
+    }
+
+    #[test]
+    fn context_limit_from_model_name() {
+        assert_eq!(context_limit("ernie-4.0-8k"), 8192);
+        assert_eq!(context_limit("ernie-speed-128k"), 131072);
+        assert_eq!(context_limit("deepseek-r1"), 65536);
+        assert_eq!(context_limit("ernie-lite"), DEFAULT_CONTEXT_LIMIT);
+        assert_eq!(reply_reserve(8192), 2048);
+        assert_eq!(reply_reserve(131072), 4096);
+    }
+
+    #[test]
+    fn budget_leaves_room_for_the_reply() {
+        assert_eq!(request_budget(8192), 8192 - 2048);
+        assert_eq!(request_budget(131072), 131072 - 4096);
+    }
+
+    #[test]
+    fn drops_oldest_turns_until_the_request_fits() {
+        let turns = [("user", 10), ("assistant", 10), ("user", 10), ("assistant", 10), ("user", 10)];
+        let tokens = 5 * (10 + MESSAGE_OVERHEAD);
+        assert_eq!(turns_to_drop(&turns, tokens, tokens), 0);
+        // One turn over: its reply goes too, so the kept turns start with a user turn
+        assert_eq!(turns_to_drop(&turns, tokens, tokens - 1), 2);
+        assert_eq!(turns_to_drop(&turns, tokens, 3 * (10 + MESSAGE_OVERHEAD) - 1), 4);
+        // The newest turn is sent even if it alone overflows
+        assert_eq!(turns_to_drop(&turns, tokens, 0), 4);
+        assert_eq!(turns_to_drop(&[("user", 100)], 104, 10), 0);
+    }
+
+    #[test]
+    fn transcript_follows_the_previous_summary() {
+        let transcript = summary_transcript(Some("old"), &[("user", "q"), ("assistant", "a")]);
+        assert_eq!(transcript, format!(
+            "{}\nold\n\n{}: q\n\n{}: a\n\n",
+            PREVIOUS_SUMMARY_LABEL, USER_LABEL, ASSISTANT_LABEL
+        ));
+        assert!(summary_transcript(None, &[]).is_empty());
+    }
+
+    #[test]
+    fn formats_token_counts() {
+        assert_eq!(format_tokens(999), "999");
+        assert_eq!(format_tokens(8192), "8k");
+        assert_eq!(format_tokens(1500), "1.5k");
+    }
+
+    #[test]
+    fn summarize_keeps_recent_transcript_within_budget() {
+        let transcript = format!("{}end", "x".repeat(400));
+        let messages = summarize_messages(&transcript, 10);
+        let text = messages[1]["content"].as_str().unwrap();
+        assert!(estimate_tokens(text) <= 10);
+        assert!(text.ends_with("end"));
+    }
+}
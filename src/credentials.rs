@@ -0,0 +1,355 @@
+use aes_gcm::aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use clap::Subcommand;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use crate::cli::{Cli, EXIT_USAGE_ERROR};
+use crate::i18n;
+use crate::Profile;
+
+/// Passphrase for the encrypted token store; prompted for when unset.
+pub const PASSPHRASE_ENV: &str = "BD_LLM_TUI_PASSPHRASE";
+
+/// Auth token encrypted with AES-256-GCM under a key derived from a passphrase
+/// with Argon2id. All fields are base64.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EncryptedToken {
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Config,
+    Env,
+    Command,
+    Encrypted,
+}
+
+pub struct Credentials {
+    pub token: String,
+    pub source: TokenSource,
+    // Kept so a token changed in the UI can be encrypted again
+    pub passphrase: Option<String>,
+    // IAM secret key, from the encrypted store or the plaintext config
+    pub secret_key: Option<String>,
+}
+
+/// Reads the auth token and the IAM secret key. Both encrypted stores share
+/// one passphrase, so it is asked for at most once.
+pub fn resolve(profile: &Profile) -> Result<Credentials> {
+    let mut credentials = resolve_token(profile)?;
+    credentials.secret_key = match &profile.encrypted_secret_key {
+        Some(encrypted) => {
+            if credentials.passphrase.is_none() {
+                credentials.passphrase = Some(read_passphrase(i18n::text().passphrase_prompt)?);
+            }
+            Some(decrypt(encrypted, credentials.passphrase.as_deref().unwrap_or_default())?)
+        }
+        None => profile.secret_key.clone(),
+    };
+    Ok(credentials)
+}
+
+// The auth token from the first configured source: environment variable,
+// command output, encrypted store, then the plaintext token
+fn resolve_token(profile: &Profile) -> Result<Credentials> {
+    if let Some(var) = &profile.auth_token_env {
+        let token = std::env::var(var).unwrap_or_default();
+        if token.trim().is_empty() {
+            bail!((i18n::text().token_env_unset)(var));
+        }
+        return Ok(Credentials { token: token.trim().to_string(), source: TokenSource::Env, passphrase: None, secret_key: None });
+    }
+    if let Some(command) = &profile.auth_token_command {
+        let token = run_token_command(command)?;
+        return Ok(Credentials { token, source: TokenSource::Command, passphrase: None, secret_key: None });
+    }
+    if let Some(encrypted) = &profile.encrypted_token {
+        let passphrase = read_passphrase(i18n::text().passphrase_prompt)?;
+        let token = decrypt(encrypted, &passphrase)?;
+        return Ok(Credentials { token, source: TokenSource::Encrypted, passphrase: Some(passphrase), secret_key: None });
+    }
+    Ok(Credentials { token: profile.auth_token.clone(), source: TokenSource::Config, passphrase: None, secret_key: None })
+}
+
+/// Whether resolving the profile's credentials will prompt for a passphrase.
+pub fn needs_passphrase(profile: &Profile) -> bool {
+    let encrypted_token = profile.auth_token_env.is_none()
+        && profile.auth_token_command.is_none()
+        && profile.encrypted_token.is_some();
+    (encrypted_token || profile.encrypted_secret_key.is_some())
+        && std::env::var_os(PASSPHRASE_ENV).is_none()
+}
+
+fn run_token_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .with_context(|| (i18n::text().token_command_error)(command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let mut message = (i18n::text().token_command_failed)(command, &output.status);
+        if !stderr.trim().is_empty() {
+            message = format!("{}: {}", message, stderr.trim());
+        }
+        bail!(message);
+    }
+    // Like `pass show`, only the first line holds the secret
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let token = stdout.lines().next().unwrap_or_default().trim();
+    if token.is_empty() {
+        bail!((i18n::text().token_command_empty)(command));
+    }
+    Ok(token.to_string())
+}
+
+fn read_passphrase(prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt)
+        .with_context(|| (i18n::text().passphrase_read_error)(PASSPHRASE_ENV))
+}
+
+pub fn encrypt(token: &str, passphrase: &str) -> Result<EncryptedToken> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, token.as_bytes())
+        .map_err(|_| anyhow!(i18n::text().encrypt_failed))?;
+    Ok(EncryptedToken {
+        salt: BASE64.encode(salt),
+        nonce: BASE64.encode(nonce),
+        ciphertext: BASE64.encode(ciphertext),
+    })
+}
+
+fn decrypt(encrypted: &EncryptedToken, passphrase: &str) -> Result<String> {
+    let decode = |field: &str| BASE64.decode(field).context(i18n::text().encrypted_token_invalid);
+    let salt = decode(&encrypted.salt)?;
+    let nonce = decode(&encrypted.nonce)?;
+    if nonce.len() != 12 {
+        bail!(i18n::text().encrypted_token_invalid);
+    }
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt)?);
+    let plaintext = cipher.decrypt(Nonce::from_slice(&nonce), decode(&encrypted.ciphertext)?.as_slice())
+        .map_err(|_| anyhow!(i18n::text().decrypt_failed))?;
+    String::from_utf8(plaintext).context(i18n::text().encrypted_token_corrupt)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key<Aes256Gcm>> {
+    let mut key = Key::<Aes256Gcm>::default();
+    argon2::Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| anyhow!((i18n::text().key_derivation_failed)(&e)))?;
+    Ok(key)
+}
+
+/// Writes a file readable only by the current user (0600 on Unix).
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files; tighten files written by older versions
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(contents)
+}
+
+// Help texts come from the locale catalog, see cli::parse
+#[derive(Subcommand)]
+pub enum TokenCommand {
+    Encrypt,
+    Decrypt,
+}
+
+/// Runs `llm_tui token` and returns the process exit code.
+pub fn run_token(cli: &Cli, command: &TokenCommand) -> i32 {
+    match update_token(cli, command) {
+        Ok(message) => {
+            eprintln!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", (i18n::text().error)(&format!("{:#}", e)));
+            EXIT_USAGE_ERROR
+        }
+    }
+}
+
+fn update_token(cli: &Cli, command: &TokenCommand) -> Result<String> {
+    let mut config = cli.load_config().map_err(|e| anyhow!(e))?;
+    let name = cli.profile_name(&config).map_err(|e| anyhow!(e))?;
+    let available = config.profile_names().join(", ");
+    let profile = config.profile_mut(&name)
+        .ok_or_else(|| anyhow!((i18n::text().profile_not_found)(&name, &available)))?;
+    let done = match command {
+        TokenCommand::Encrypt => {
+            encrypt_profile(profile)?;
+            i18n::text().token_encrypted
+        }
+        TokenCommand::Decrypt => {
+            decrypt_profile(profile)?;
+            i18n::text().token_decrypted
+        }
+    };
+    config.save()?;
+    Ok(done(&name, &config.path.display()))
+}
+
+// Encrypts the auth token and the IAM secret key, whichever are set
+fn encrypt_profile(profile: &mut Profile) -> Result<()> {
+    if profile.encrypted_token.is_some() || profile.encrypted_secret_key.is_some() {
+        bail!(i18n::text().token_already_encrypted);
+    }
+    let secret_key = profile.secret_key.clone().filter(|key| !key.is_empty());
+    if profile.auth_token.is_empty() && secret_key.is_none() {
+        bail!(i18n::text().token_missing);
+    }
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            let text = i18n::text();
+            let passphrase = rpassword::prompt_password(text.passphrase_new)?;
+            if rpassword::prompt_password(text.passphrase_repeat)? != passphrase {
+                bail!(text.passphrase_mismatch);
+            }
+            passphrase
+        }
+    };
+    if passphrase.is_empty() {
+        bail!(i18n::text().passphrase_empty);
+    }
+    if !profile.auth_token.is_empty() {
+        profile.encrypted_token = Some(encrypt(&profile.auth_token, &passphrase)?);
+        profile.auth_token.clear();
+    }
+    if let Some(secret_key) = secret_key {
+        profile.encrypted_secret_key = Some(encrypt(&secret_key, &passphrase)?);
+        profile.secret_key = None;
+    }
+    Ok(())
+}
+
+fn decrypt_profile(profile: &mut Profile) -> Result<()> {
+    if profile.encrypted_token.is_none() && profile.encrypted_secret_key.is_none() {
+        bail!(i18n::text().token_not_encrypted);
+    }
+    let passphrase = read_passphrase(i18n::text().passphrase_prompt)?;
+    if let Some(encrypted) = profile.encrypted_token.take() {
+        profile.auth_token = decrypt(&encrypted, &passphrase)?;
+    }
+    if let Some(encrypted) = profile.encrypted_secret_key.take() {
+        profile.secret_key = Some(decrypt(&encrypted, &passphrase)?);
+    }
+    Ok(())
+}
+
+pub fn mask(token: &str) -> String {
+    "•".repeat(token.chars().count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_round_trip() {
+        let encrypted = encrypt("secret-token", "passphrase").unwrap();
+        assert_eq!(decrypt(&encrypted, "passphrase").unwrap(), "secret-token");
+        // A fresh salt and nonce every time
+        let again = encrypt("secret-token", "passphrase").unwrap();
+        assert_ne!(encrypted.ciphertext, again.ciphertext);
+    }
+
+    #[test]
+    fn wrong_passphrase_fails() {
+        let encrypted = encrypt("secret-token", "passphrase").unwrap();
+        assert!(decrypt(&encrypted, "wrong").is_err());
+        let truncated = EncryptedToken { nonce: BASE64.encode([0u8; 4]), ..encrypted };
+        assert!(decrypt(&truncated, "passphrase").is_err());
+    }
+
+    #[test]
+    fn sources_take_precedence_in_order() {
+        let var = format!("LLM_TUI_TEST_TOKEN_{}", std::process::id());
+        std::env::set_var(&var, " env-token\n");
+        std::env::set_var(PASSPHRASE_ENV, "passphrase");
+        let mut profile = Profile {
+            auth_token: "plain-token".to_string(),
+            auth_token_env: Some(var.clone()),
+            auth_token_command: Some("echo command-token".to_string()),
+            encrypted_token: Some(encrypt("encrypted-token", "passphrase").unwrap()),
+            ..Profile::default()
+        };
+        let resolved = |profile: &Profile| resolve(profile).map(|c| (c.token, c.source)).unwrap();
+
+        assert!(resolved(&profile) == ("env-token".to_string(), TokenSource::Env));
+        profile.auth_token_env = None;
+        assert!(resolved(&profile) == ("command-token".to_string(), TokenSource::Command));
+        profile.auth_token_command = None;
+        assert!(resolved(&profile) == ("encrypted-token".to_string(), TokenSource::Encrypted));
+        profile.encrypted_token = None;
+        assert!(resolved(&profile) == ("plain-token".to_string(), TokenSource::Config));
+
+        // A configured but empty variable is an error, not a fallback
+        std::env::set_var(&var, "");
+        profile.auth_token_env = Some(var.clone());
+        assert!(resolve(&profile).is_err());
+        std::env::remove_var(&var);
+    }
+
+    #[test]
+    fn secret_key_shares_the_token_passphrase() {
+        std::env::set_var(PASSPHRASE_ENV, "passphrase");
+        let mut profile = Profile {
+            auth_token: "plain-token".to_string(),
+            secret_key: Some("plain-sk".to_string()),
+            ..Profile::default()
+        };
+        encrypt_profile(&mut profile).unwrap();
+        assert!(profile.auth_token.is_empty() && profile.secret_key.is_none());
+        let credentials = resolve(&profile).unwrap();
+        assert_eq!(credentials.token, "plain-token");
+        assert_eq!(credentials.secret_key.as_deref(), Some("plain-sk"));
+
+        decrypt_profile(&mut profile).unwrap();
+        assert!(profile.encrypted_token.is_none() && profile.encrypted_secret_key.is_none());
+        assert_eq!(profile.secret_key.as_deref(), Some("plain-sk"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("llm_tui_credentials_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.json");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        write_private(&path, b"new").unwrap();
+        assert_eq!(mode(&path), 0o600);
+        // Files written by older versions are tightened as well
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_private(&path, b"again").unwrap();
+        let result = (mode(&path), fs::read_to_string(&path).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(result, (0o600, "again".to_string()));
+    }
+}
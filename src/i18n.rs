@@ -0,0 +1,914 @@
+use anyhow::{bail, Result};
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Locale {
+    ZhCn,
+    EnUs,
+}
+
+impl Locale {
+    /// Parses a `language` config value such as `zh-CN` or `en-US`.
+    pub fn parse(name: &str) -> Result<Locale> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "zh" | "zh-cn" => Ok(Locale::ZhCn),
+            "en" | "en-us" => Ok(Locale::EnUs),
+            _ => bail!((text().language_unsupported)(name)),
+        }
+    }
+
+    // From the POSIX locale variables, in order of precedence. Chinese unless
+    // a non-Chinese locale is set, so existing setups look the same.
+    fn from_env() -> Locale {
+        let value = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty());
+        match value.as_deref() {
+            None | Some("C" | "POSIX") => Locale::ZhCn,
+            Some(value) if value.starts_with("C.") || value.starts_with("zh") => Locale::ZhCn,
+            Some(_) => Locale::EnUs,
+        }
+    }
+}
+
+static LOCALE: OnceLock<Locale> = OnceLock::new();
+
+/// Sets the UI language from the `language` config value, or from `LANG`
+/// when it is unset. Until then `text()` follows `LANG`.
+pub fn init(language: Option<&str>) -> Result<()> {
+    let locale = match language {
+        Some(name) => Locale::parse(name)?,
+        None => Locale::from_env(),
+    };
+    let _ = LOCALE.set(locale);
+    Ok(())
+}
+
+/// The strings of the current locale.
+pub fn text() -> &'static Text {
+    // Not get_or_init: messages printed while the config loads must not
+    // lock in the environment's language before `init` runs
+    match LOCALE.get().copied().unwrap_or_else(Locale::from_env) {
+        Locale::ZhCn => &ZH_CN,
+        Locale::EnUs => &EN_US,
+    }
+}
+
+/// User-facing strings of the TUI. Messages with arguments are functions.
+pub struct Text {
+    // Roles as shown in the history
+    pub role_user: &'static str,
+    pub role_assistant: &'static str,
+    pub role_system: &'static str,
+    pub role_summary: &'static str,
+
+    // Pane and popup titles
+    pub title_input: fn(&str, &str, &str) -> String,
+    pub title_history: &'static str,
+    pub title_comparison: &'static str,
+    pub title_help: &'static str,
+    pub title_config: &'static str,
+    pub title_import: &'static str,
+    pub title_session_search: fn(usize) -> String,
+    pub title_profile_select: fn(&str) -> String,
+    pub title_compare_select: &'static str,
+    pub title_model_select: fn(&str) -> String,
+    pub title_sidebar: &'static str,
+    pub sidebar_empty: &'static str,
+    pub title_attach: &'static str,
+
+    // Help menu
+    pub help_heading: &'static str,
+    pub help_scroll: &'static str,
+    pub help_quit: &'static str,
+    pub help_close_popup: &'static str,
+    pub help_complete_reference: &'static str,
+    pub help_vim_heading: &'static str,
+    pub help_vim_scroll: &'static str,
+    pub help_vim_jump: &'static str,
+    pub help_vim_half_page: &'static str,
+    pub help_vim_search: &'static str,
+    pub help_vim_next: &'static str,
+    pub help_vim_copy: &'static str,
+    pub help_vim_insert: &'static str,
+    pub help_keybindings: &'static str,
+    pub action_help: &'static str,
+    pub action_config: &'static str,
+    pub action_select_model: &'static str,
+    pub action_copy_reply: &'static str,
+    pub action_import: &'static str,
+    pub action_compare_models: &'static str,
+    pub action_switch_profile: &'static str,
+    pub action_search: &'static str,
+    pub action_search_sessions: &'static str,
+    pub action_attach: &'static str,
+    pub action_cycle_layout: &'static str,
+    pub action_grow_input: &'static str,
+    pub action_shrink_input: &'static str,
+    pub action_toggle_sidebar: &'static str,
+    pub action_zen_mode: &'static str,
+    pub action_switch_focus: &'static str,
+    pub action_send: &'static str,
+    pub action_quit: &'static str,
+
+    // Status bar
+    pub status_waiting: fn(&str, f64) -> String,
+    pub status_requests: fn(usize) -> String,
+    pub status_queued: fn(usize) -> String,
+    pub status_retry: fn(u32, u32) -> String,
+    pub status_retry_in: fn(u64) -> String,
+    pub status_command: fn(&str, &str, f64) -> String,
+    pub status_ready: &'static str,
+    pub status_compare: fn(&str) -> String,
+    pub status_profile: fn(&str) -> String,
+    pub status_session: fn(&str) -> String,
+    pub status_unsaved: &'static str,
+    pub status_context: fn(&str, &str) -> String,
+    pub status_latency: fn(f64) -> String,
+    pub status_usage: fn(u64) -> String,
+    pub usage_details: fn(u64, u64) -> String,
+
+    // History search
+    pub search_regex: &'static str,
+    pub search_case_sensitive: &'static str,
+    pub search_hint: &'static str,
+    pub search_no_match: fn(&str) -> String,
+    pub search_position: fn(&str, usize, usize) -> String,
+    pub search_count: fn(&str, usize) -> String,
+    pub invalid_regex: fn(&str) -> String,
+
+    // Session search
+    pub session_skipped: fn(&str) -> String,
+    pub session_search_hint: &'static str,
+    pub session_search_empty: &'static str,
+    pub session_wait_pending: &'static str,
+    pub session_missing: fn(&str) -> String,
+    pub session_load_error: fn(&str) -> String,
+    pub session_save_error: fn(&str) -> String,
+    pub session_loaded: fn(&str, usize, &str) -> String,
+    pub session_read_error: fn(&dyn Display) -> String,
+    pub session_format_error: fn(&dyn Display) -> String,
+
+    // Requests and replies
+    pub waiting_response: &'static str,
+    pub reply_failed: &'static str,
+    pub reply_pending: &'static str,
+    pub no_auth: &'static str,
+    pub error: fn(&str) -> String,
+    pub request_error: fn(&str) -> String,
+    pub parse_error: fn(&str) -> String,
+    pub api_error: fn(&str) -> String,
+    pub no_content: fn(&str) -> String,
+    pub unknown_error: &'static str,
+    pub attempts: fn(&str, u32) -> String,
+    pub aksk_failed: &'static str,
+    pub iam_url_invalid: fn(&str) -> String,
+    pub no_iam_token: fn(&str) -> String,
+
+    // Context window
+    pub marker_summarizing: fn(usize) -> String,
+    pub marker_summarized: fn(usize) -> String,
+    pub marker_summary_failed: fn(usize, &str) -> String,
+    pub marker_truncated: fn(usize) -> String,
+
+    // Models, profiles and comparison
+    pub model_switched: fn(&str) -> String,
+    pub compare_on: fn(&str) -> String,
+    pub compare_off: &'static str,
+    pub compare_adopted: fn(&str) -> String,
+    pub compare_discarded: &'static str,
+    pub profile_switched: fn(&str, &str, &str) -> String,
+    pub profile_no_auth: &'static str,
+    pub profile_unknown_model: fn(&str) -> String,
+    pub profile_switch_failed: fn(&str) -> String,
+    pub profile_current: &'static str,
+    pub config_save_error: fn(&str) -> String,
+    pub layout_save_error: fn(&str) -> String,
+    pub token_external: &'static str,
+    pub copied: &'static str,
+    pub copy_failed: fn(&str) -> String,
+
+    // Attachments
+    pub attachment_header: fn(&str) -> String,
+    pub attachment_too_large: fn(&str, &str) -> String,
+    pub attachment_binary: fn(&str) -> String,
+    pub attach_failed: fn(&str) -> String,
+    pub attachment_store_error: fn(&dyn Display) -> String,
+    pub attach_remove_hint: &'static str,
+    pub reference_failed: fn(&str) -> String,
+    pub command_header: fn(&str, Option<i32>) -> String,
+    pub command_attached: fn(usize) -> String,
+    pub command_busy: &'static str,
+    pub command_failed: fn(&str) -> String,
+    pub command_timeout: fn(&str, u64) -> String,
+    pub command_truncated: &'static str,
+    pub vision_unsupported: fn(&str) -> String,
+
+    // Import
+    pub imported: fn(usize, &str) -> String,
+    pub imported_model: fn(&str) -> String,
+    pub imported_continue: fn(&str) -> String,
+    pub imported_system_prompt: &'static str,
+    pub import_failed: fn(&str) -> String,
+    pub import_format_chatgpt: &'static str,
+    pub import_no_path: &'static str,
+    pub import_read_error: fn(&dyn Display) -> String,
+    pub import_empty: fn(&dyn Display) -> String,
+    pub import_no_messages: &'static str,
+    pub import_index_from_one: &'static str,
+    pub import_only_n: fn(usize) -> String,
+    pub import_picked: fn(usize, usize) -> String,
+    pub import_bad_file: fn(&str) -> String,
+    pub import_messages_not_array: &'static str,
+    pub import_unknown_format: &'static str,
+    pub import_invalid_role: fn(usize, &str) -> String,
+    pub import_invalid_json_line: fn(usize, &str) -> String,
+    pub import_line: fn(usize) -> String,
+    pub import_message: fn(usize) -> String,
+    pub import_line_message: fn(&str, usize) -> String,
+    pub import_mixed_jsonl: &'static str,
+    pub import_missing_role: &'static str,
+    pub import_unsupported_role: fn(&str) -> String,
+    pub import_missing_content: &'static str,
+    pub import_bad_content: &'static str,
+    pub import_chatgpt_error: fn(&str) -> String,
+    pub import_chatgpt_mapping: &'static str,
+    pub import_chatgpt_no_node: &'static str,
+    pub import_chatgpt_cycle: &'static str,
+    pub import_skipped: fn(usize) -> String,
+    pub import_title: fn(&str) -> String,
+
+    // Command line
+    pub cli_about: &'static str,
+    pub cli_prompt: &'static str,
+    pub cli_model: &'static str,
+    pub cli_config: &'static str,
+    pub cli_endpoint: &'static str,
+    pub cli_profile: &'static str,
+    pub cli_session: &'static str,
+    pub cli_ask: &'static str,
+    pub cli_ask_prompt: &'static str,
+    pub cli_ask_stdin: &'static str,
+    pub cli_ask_system: &'static str,
+    pub cli_ask_json: &'static str,
+    pub cli_ask_curl: &'static str,
+    pub cli_batch: &'static str,
+    pub cli_batch_input: &'static str,
+    pub cli_batch_output: &'static str,
+    pub cli_batch_concurrency: &'static str,
+    pub cli_batch_rate: &'static str,
+    pub cli_token: &'static str,
+    pub cli_token_encrypt: &'static str,
+    pub cli_token_decrypt: &'static str,
+    pub cli_no_auth: &'static str,
+    pub cli_prompt_missing: &'static str,
+    pub cli_prompt_empty: &'static str,
+    pub cli_stdin_error: fn(&dyn Display) -> String,
+    pub cli_retrying: fn(&str, f64, u32, u32) -> String,
+    pub unknown_model: fn(&str) -> String,
+    pub endpoint_invalid: &'static str,
+    pub profile_not_found: fn(&str, &str) -> String,
+    pub profile_invalid_model: fn(&str, &str, &str) -> String,
+    pub session_name_empty: &'static str,
+    pub session_name_invalid: fn(&str) -> String,
+    pub language_unsupported: fn(&str) -> String,
+    pub clipboard_init_failed: &'static str,
+
+    // Config file and data directories
+    pub config_not_found: fn(&dyn Display) -> String,
+    pub config_read_error: fn(&dyn Display) -> String,
+    pub config_invalid: fn(&dyn Display) -> String,
+    pub config_upgrade_error: fn(&dyn Display) -> String,
+    pub config_backup_error: fn(&dyn Display) -> String,
+    pub config_json_syntax: fn(&dyn Display) -> String,
+    pub config_toml_syntax: fn(&dyn Display) -> String,
+    pub config_toml_error: &'static str,
+    pub config_field: fn(&dyn Display, &dyn Display) -> String,
+    pub config_not_object: &'static str,
+    pub config_bad_version: &'static str,
+    pub config_too_new: fn(u64, u64) -> String,
+    pub legacy_migrated: fn(&dyn Display, &dyn Display) -> String,
+    pub legacy_migrate_failed: fn(&str) -> String,
+    pub move_failed: fn(&dyn Display, &dyn Display) -> String,
+
+    // Credentials
+    pub token_env_unset: fn(&str) -> String,
+    pub token_command_error: fn(&str) -> String,
+    pub token_command_failed: fn(&str, &dyn Display) -> String,
+    pub token_command_empty: fn(&str) -> String,
+    pub passphrase_prompt: &'static str,
+    pub passphrase_new: &'static str,
+    pub passphrase_repeat: &'static str,
+    pub passphrase_read_error: fn(&str) -> String,
+    pub passphrase_mismatch: &'static str,
+    pub passphrase_empty: &'static str,
+    pub encrypt_failed: &'static str,
+    pub encrypted_token_invalid: &'static str,
+    pub decrypt_failed: &'static str,
+    pub encrypted_token_corrupt: &'static str,
+    pub key_derivation_failed: fn(&dyn Display) -> String,
+    pub token_already_encrypted: &'static str,
+    pub token_not_encrypted: &'static str,
+    pub token_missing: &'static str,
+    pub token_encrypted: fn(&str, &dyn Display) -> String,
+    pub token_decrypted: fn(&str, &dyn Display) -> String,
+
+    // Key bindings and themes
+    pub key_empty: &'static str,
+    pub key_unknown_modifier: fn(&str) -> String,
+    pub key_unknown: fn(&str) -> String,
+    pub key_reserved_quit: &'static str,
+    pub key_reserved_typing: &'static str,
+    pub key_reserved_editing: &'static str,
+    pub key_conflict: fn(&dyn Display, &str, &str) -> String,
+    pub keybindings_invalid: fn(&str) -> String,
+    pub theme_not_found: fn(&str, &str) -> String,
+    pub theme_bad_base: fn(&str, &str) -> String,
+    pub theme_bad_style: fn(&str, &str, &dyn Display) -> String,
+    pub theme_unknown_element: fn(&str, &str) -> String,
+    pub color_unknown: fn(&str) -> String,
+
+    // Batch
+    pub batch_rate_invalid: &'static str,
+    pub batch_input_error: fn(&dyn Display, &dyn Display) -> String,
+    pub batch_output_error: fn(&dyn Display, &dyn Display) -> String,
+    pub batch_results_error: fn(&dyn Display, &dyn Display) -> String,
+    pub batch_line: fn(usize, &str) -> String,
+    pub batch_duplicate_id: fn(&str) -> String,
+    pub batch_invalid_request: fn(&dyn Display) -> String,
+    pub batch_reserved_id: fn(&str) -> String,
+    pub batch_bad_id: &'static str,
+    pub batch_unknown_model: fn(&str) -> String,
+    pub batch_start: fn(usize, &dyn Display) -> String,
+    pub batch_resume: fn(usize, usize, &dyn Display) -> String,
+    pub batch_failed: fn(usize, usize, &str, &str) -> String,
+    pub batch_succeeded: fn(usize, usize, &str, u64) -> String,
+    pub batch_done: fn(usize, usize) -> String,
+}
+
+static ZH_CN: Text = Text {
+    role_user: "你",
+    role_assistant: "AI",
+    role_system: "系统",
+    role_summary: "摘要",
+
+    title_input: |send, config, help| format!("输入 ({}发送, {}配置, {}帮助)", send, config, help),
+    title_history: "对话历史 (↑/↓滚动)",
+    title_comparison: "模型对比 (←/→选择, ↑/↓滚动, Enter采用, Esc放弃)",
+    title_help: "帮助",
+    title_config: "输入认证令牌 (当前令牌已保存)",
+    title_import: "导入对话 (文件路径，多对话文件可用 路径#序号)",
+    title_session_search: |count| format!("搜索所有会话 ({} 个会话, ↑/↓选择, Enter打开, Esc关闭)", count),
+    title_profile_select: |current| format!("切换配置档案 (当前: {})", current),
+    title_compare_select: "选择对比模型 (空格勾选, Enter确认)",
+    title_model_select: |current| format!("选择模型 (当前: {})", current),
+    title_sidebar: "会话",
+    sidebar_empty: "(没有已保存的会话)",
+    title_attach: "附加文件 (图片或文本文件路径)",
+
+    help_heading: "帮助菜单:",
+    help_scroll: "在历史框中滚动",
+    help_quit: "退出程序",
+    help_close_popup: "关闭弹窗",
+    help_complete_reference: "补全输入末尾的 @文件 引用",
+    help_vim_heading: "Vim 模式 (普通模式下):",
+    help_vim_scroll: "向下/向上滚动历史",
+    help_vim_jump: "跳到历史开头/末尾",
+    help_vim_half_page: "向下/向上滚动半页",
+    help_vim_search: "搜索历史",
+    help_vim_next: "下一个/上一个匹配",
+    help_vim_copy: "复制最后一条AI回复",
+    help_vim_insert: "进入插入模式 (插入模式下 Esc 返回)",
+    help_keybindings: "(可在配置文件的 keybindings 中修改快捷键)",
+    action_help: "显示此帮助菜单",
+    action_config: "配置认证令牌",
+    action_select_model: "选择模型",
+    action_copy_reply: "复制最后一条AI回复",
+    action_import: "导入对话 (JSON/JSONL)",
+    action_compare_models: "选择对比模型 (空格勾选，两个以上开启对比模式)",
+    action_switch_profile: "切换配置档案",
+    action_search: "搜索历史 (历史框中也可按 /，n/N 跳到下一个/上一个匹配)",
+    action_search_sessions: "搜索所有已保存的会话",
+    action_attach: "附加图片或文本文件",
+    action_cycle_layout: "切换布局 (输入框在上/在下/在右)",
+    action_grow_input: "放大输入框",
+    action_shrink_input: "缩小输入框",
+    action_toggle_sidebar: "显示/隐藏会话侧栏",
+    action_zen_mode: "专注模式 (只显示对话历史)",
+    action_switch_focus: "切换输入框和历史框",
+    action_send: "发送请求",
+    action_quit: "退出程序",
+
+    status_waiting: |frame, seconds| format!(" {} 等待响应 {:.1}s", frame, seconds),
+    status_requests: |count| format!(" · 共 {} 个请求", count),
+    status_queued: |count| format!(" · 排队 {} 条", count),
+    status_retry: |attempt, max_attempts| format!(" · 重试 {}/{}", attempt, max_attempts),
+    status_retry_in: |seconds| format!(" ({}s 后)", seconds),
+    status_command: |frame, command, seconds| format!(" {} 运行 {} {:.1}s", frame, command, seconds),
+    status_ready: " ● 就绪",
+    status_compare: |models| format!("对比: {}", models),
+    status_profile: |name| format!("档案: {}", name),
+    status_session: |name| format!("会话: {}", name),
+    status_unsaved: "未保存",
+    status_context: |tokens, limit| format!("上下文: ~{}/{}", tokens, limit),
+    status_latency: |seconds| format!("延迟: {:.1}s", seconds),
+    status_usage: |tokens| format!("用量: {} tokens", tokens),
+    usage_details: |input, output| format!(" · 输入 {} / 输出 {} tokens", input, output),
+
+    search_regex: "  [正则]",
+    search_case_sensitive: "  [区分大小写]",
+    search_hint: "  (Ctrl+R 正则, Ctrl+T 大小写, Enter 搜索, Esc 取消)",
+    search_no_match: |query| format!("搜索 \"{}\": 无匹配", query),
+    search_position: |query, current, count| format!("搜索 \"{}\": {}/{}", query, current, count),
+    search_count: |query, count| format!("搜索 \"{}\": {} 处匹配", query, count),
+    invalid_regex: |error| format!("正则表达式无效: {}", error),
+
+    session_skipped: |error| format!("已跳过: {}", error),
+    session_search_hint: "输入关键词搜索所有已保存的会话",
+    session_search_empty: "没有匹配的消息",
+    session_wait_pending: "请等待当前请求完成后再切换会话",
+    session_missing: |name| format!("会话 {} 已不存在", name),
+    session_load_error: |error| format!("会话载入错误: {}", error),
+    session_save_error: |error| format!("会话保存错误: {}", error),
+    session_loaded: |name, count, model| format!("已载入会话 {} ({} 条消息)，当前模型: {}", name, count, model),
+    session_read_error: |path| format!("无法读取会话文件 {}", path),
+    session_format_error: |path| format!("会话文件格式错误 {}", path),
+
+    waiting_response: "正在等待响应...",
+    reply_failed: " · 失败",
+    reply_pending: " · 等待中",
+    no_auth: "请先配置API认证令牌或 AK/SK",
+    error: |error| format!("错误: {}", error),
+    request_error: |error| format!("请求错误: {}", error),
+    parse_error: |error| format!("响应解析错误: {}", error),
+    api_error: |error| format!("API错误: {}", error),
+    no_content: |text| format!("响应中没有内容: {}", text),
+    unknown_error: "未知错误",
+    attempts: |error, attempts| format!("{} (已尝试 {} 次)", error, attempts),
+    aksk_failed: "AK/SK 认证失败",
+    iam_url_invalid: |url| format!("IAM 地址无效: {}", url),
+    no_iam_token: |text| format!("响应中没有令牌: {}", text),
+
+    marker_summarizing: |count| format!("⋯ 以上 {} 条消息超出上下文窗口，正在压缩为摘要 ⋯", count),
+    marker_summarized: |count| format!("⋯ 以上 {} 条消息已压缩为下方的摘要 ⋯", count),
+    marker_summary_failed: |count, error| format!("⋯ 以上 {} 条消息超出上下文窗口，已不再发送 (生成摘要失败: {}) ⋯", count, error),
+    marker_truncated: |count| format!("⋯ 以上 {} 条消息超出上下文窗口，已不再发送 ⋯", count),
+
+    model_switched: |model| format!("已切换到模型: {}", model),
+    compare_on: |models| format!("对比模式: {}", models),
+    compare_off: "已关闭对比模式",
+    compare_adopted: |model| format!("已采用 {} 的回复，已关闭对比模式并使用该模型继续对话", model),
+    compare_discarded: "已放弃本轮对比结果，问题已从对话中移除",
+    profile_switched: |name, model, url| format!("已切换到配置档案: {}，模型: {}，地址: {}", name, model, url),
+    profile_no_auth: "该档案尚未配置认证令牌或 AK/SK",
+    profile_unknown_model: |model| format!("该档案的模型 {} 不在可用模型列表中，继续使用当前模型", model),
+    profile_switch_failed: |error| format!("切换配置档案失败: {}", error),
+    profile_current: "  [当前]",
+    config_save_error: |error| format!("配置保存错误: {}", error),
+    layout_save_error: |error| format!("布局保存错误: {}", error),
+    token_external: "令牌由 auth_token_env 或 auth_token_command 提供，此处修改仅对本次运行有效",
+    copied: "已复制到剪贴板",
+    copy_failed: |error| format!("复制到剪贴板失败: {}", error),
+
+    attachment_header: |name| format!("文件 {}:", name),
+    attachment_too_large: |name, limit| format!("{} 超过了 {} 的大小限制", name, limit),
+    attachment_binary: |name| format!("{} 不是图片或 UTF-8 文本文件", name),
+    attach_failed: |error| format!("附加文件失败: {}", error),
+    attachment_store_error: |path| format!("无法保存图片: {}", path),
+    attach_remove_hint: " (输入为空时按 Backspace 移除)",
+    reference_failed: |error| format!("无法展开 @ 引用，消息未发送: {}", error),
+    command_header: |command, code| match code {
+        Some(code) => format!("命令 `{}` 的输出 (退出码 {}):", command, code),
+        None => format!("命令 `{}` 的输出 (被信号终止):", command),
+    },
+    command_attached: |lines| format!("(共 {} 行输出，已全部附加到下一条消息，输入为空时按 Backspace 移除)", lines),
+    command_busy: "已有命令在运行，请等待其结束",
+    command_failed: |command| format!("无法运行命令: {}", command),
+    command_timeout: |command, seconds| format!("命令 {} 超过 {} 秒未结束，已终止", command, seconds),
+    command_truncated: "…(输出过长，仅保留末尾部分)",
+    vision_unsupported: |model| format!("{} 不支持图片，请切换到支持图片的模型 (可在配置文件的 vision_models 中添加)", model),
+
+    imported: |count, format| format!("已导入 {} 条消息 (格式: {})", count, format),
+    imported_model: |model| format!("，原模型: {}", model),
+    imported_continue: |model| format!("，将使用 {} 继续对话", model),
+    imported_system_prompt: "，已载入系统提示词",
+    import_failed: |error| format!("导入失败: {}", error),
+    import_format_chatgpt: "ChatGPT 导出",
+    import_no_path: "请输入要导入的文件路径",
+    import_read_error: |path| format!("无法读取文件 {}", path),
+    import_empty: |path| format!("文件为空: {}", path),
+    import_no_messages: "文件中没有可导入的用户或AI消息",
+    import_index_from_one: "对话序号从 1 开始",
+    import_only_n: |count| format!("文件中只有 {} 个对话", count),
+    import_picked: |count, index| format!("文件包含 {} 个对话，已导入第 {} 个 (可用 路径#序号 选择)", count, index),
+    import_bad_file: |error| format!("对话文件格式错误: {}", error),
+    import_messages_not_array: "messages 字段必须是数组",
+    import_unknown_format: "无法识别的对话格式",
+    import_invalid_role: |index, role| format!("第 {} 条消息的角色无效: {}", index, role),
+    import_invalid_json_line: |line, error| format!("第 {} 行不是有效的 JSON: {}", line, error),
+    import_line: |line| format!("第 {} 行", line),
+    import_message: |index| format!("第 {} 条消息", index),
+    import_line_message: |line, index| format!("{} 第 {} 条消息", line, index),
+    import_mixed_jsonl: "JSONL 文件混合了对话记录和单条消息",
+    import_missing_role: "缺少 role 字段",
+    import_unsupported_role: |role| format!("不支持的角色 \"{}\"", role),
+    import_missing_content: "缺少 content 字段",
+    import_bad_content: "content 字段必须是字符串或内容数组",
+    import_chatgpt_error: |error| format!("ChatGPT 导出格式错误: {}", error),
+    import_chatgpt_mapping: "mapping 字段必须是对象",
+    import_chatgpt_no_node: "找不到当前节点",
+    import_chatgpt_cycle: "节点存在循环引用",
+    import_skipped: |count| format!("已跳过 {} 条非文本或工具消息", count),
+    import_title: |title| format!("对话标题: {}", title),
+
+    // Command line
+    cli_about: "百度千帆大语言模型终端界面",
+    cli_prompt: "启动后立即发送的问题",
+    cli_model: "使用的模型 (默认 deepseek-r1)",
+    cli_config: "配置文件路径",
+    cli_endpoint: "API 地址",
+    cli_profile: "使用的配置档案",
+    cli_session: "会话名称：启动时载入该会话，并在每次回复后自动保存",
+    cli_ask: "非交互模式：发送一个问题并将回复输出到标准输出",
+    cli_ask_prompt: "问题内容；省略或为 - 时从标准输入读取",
+    cli_ask_stdin: "读取标准输入并附加在问题之后",
+    cli_ask_system: "系统提示词",
+    cli_ask_json: "以 JSON 格式输出回复、用量和耗时",
+    cli_ask_curl: "只打印等价的 curl 命令（令牌写作 $TOKEN），不发送请求",
+    cli_batch: "批量处理 JSONL 文件中的请求，结果写入 JSONL 文件",
+    cli_batch_input: "输入文件，每行一个 JSON 请求: {\"id\", \"prompt\", \"model\", \"system\", \"params\"}",
+    cli_batch_output: "结果文件 (默认: <输入文件名>.results.jsonl)；已存在时跳过其中成功的请求",
+    cli_batch_concurrency: "同时进行的请求数",
+    cli_batch_rate: "每秒最多发出的请求数 (0 表示不限制)",
+    cli_token: "管理认证令牌和 Secret Key 的加密存储",
+    cli_token_encrypt: "用口令加密配置文件中的认证令牌和 Secret Key",
+    cli_token_decrypt: "解密认证令牌和 Secret Key 并以明文保存",
+    cli_no_auth: "请先配置API认证令牌或 AK/SK (运行 llm_tui 后按 Alt+C)",
+    cli_prompt_missing: "缺少问题内容 (可作为参数提供或通过管道输入)",
+    cli_prompt_empty: "问题内容为空",
+    cli_stdin_error: |error| format!("读取标准输入失败: {}", error),
+    cli_retrying: |error, delay, attempt, max_attempts| format!("{}，{:.1}s 后重试 ({}/{})", error, delay, attempt, max_attempts),
+    unknown_model: |models| format!("未知模型，可用模型: {}", models),
+    endpoint_invalid: "API 地址必须以 http:// 或 https:// 开头",
+    profile_not_found: |name, available| format!("未找到配置档案: {} (可用: {})", name, available),
+    profile_invalid_model: |name, model, error| format!("配置档案 {} 的模型 {} 无效: {}", name, model, error),
+    session_name_empty: "会话名称不能为空",
+    session_name_invalid: |name| format!("会话名称不能包含路径分隔符或以 . 开头: {}", name),
+    language_unsupported: |name| format!("不支持的语言: {} (可用: zh-CN, en-US)", name),
+    clipboard_init_failed: "无法初始化剪贴板",
+
+    // Config file and data directories
+    config_not_found: |path| format!("配置文件不存在: {}", path),
+    config_read_error: |path| format!("无法读取配置文件 {}", path),
+    config_invalid: |path| format!("配置文件 {} 有误", path),
+    config_upgrade_error: |path| format!("无法写入升级后的配置文件 {}", path),
+    config_backup_error: |path| format!("无法备份配置文件 {}", path),
+    config_json_syntax: |error| format!("JSON 语法错误: {}", error),
+    config_toml_syntax: |error| format!("TOML 语法错误: {}", error),
+    config_toml_error: "无法转换为 TOML",
+    config_field: |path, error| format!("字段 {}: {}", path, error),
+    config_not_object: "配置文件顶层必须是对象",
+    config_bad_version: "字段 version: 必须是非负整数",
+    config_too_new: |version, supported| format!("配置文件版本 {} 高于本程序支持的版本 {}，请升级 llm_tui", version, supported),
+    legacy_migrated: |from, to| format!("已将配置和会话从 {} 迁移到 {}", from, to),
+    legacy_migrate_failed: |error| format!("警告: 迁移旧配置目录失败: {}", error),
+    move_failed: |from, to| format!("无法将 {} 移动到 {}", from, to),
+
+    // Credentials
+    token_env_unset: |var| format!("环境变量 {} 未设置 (auth_token_env)", var),
+    token_command_error: |command| format!("无法执行令牌命令 `{}`", command),
+    token_command_failed: |command, status| format!("令牌命令 `{}` 执行失败 ({})", command, status),
+    token_command_empty: |command| format!("令牌命令 `{}` 没有输出", command),
+    passphrase_prompt: "请输入令牌口令: ",
+    passphrase_new: "设置令牌口令: ",
+    passphrase_repeat: "再次输入口令: ",
+    passphrase_read_error: |var| format!("无法读取口令 (可通过环境变量 {} 提供)", var),
+    passphrase_mismatch: "两次输入的口令不一致",
+    passphrase_empty: "口令不能为空",
+    encrypt_failed: "令牌加密失败",
+    encrypted_token_invalid: "encrypted_token 格式错误",
+    decrypt_failed: "口令错误或加密令牌已损坏",
+    encrypted_token_corrupt: "加密令牌已损坏",
+    key_derivation_failed: |error| format!("密钥派生失败: {}", error),
+    token_already_encrypted: "认证令牌或 Secret Key 已经加密，请先解密",
+    token_not_encrypted: "认证令牌和 Secret Key 都没有加密",
+    token_missing: "配置文件中没有认证令牌或 Secret Key",
+    token_encrypted: |profile, path| format!("档案 {} 的凭据已加密保存到 {}", profile, path),
+    token_decrypted: |profile, path| format!("档案 {} 的凭据已以明文保存到 {}", profile, path),
+
+    // Key bindings and themes
+    key_empty: "按键为空",
+    key_unknown_modifier: |modifier| format!("未知的修饰键: {}", modifier),
+    key_unknown: |key| format!("未知的按键: {}", key),
+    key_reserved_quit: "固定用于退出程序",
+    key_reserved_typing: "会与文字输入冲突",
+    key_reserved_editing: "用于编辑和滚动",
+    key_conflict: |key, first, second| format!("{} 同时绑定到 {} 和 {}", key, first, second),
+    keybindings_invalid: |errors| format!("快捷键配置有误:\n  {}", errors),
+    theme_not_found: |name, available| format!("未找到主题: {} (可用: {})", name, available),
+    theme_bad_base: |name, builtin| format!("主题 {} 的 base 必须是内置主题 ({})", name, builtin),
+    theme_bad_style: |name, element, error| format!("主题 {} 的 {}: {}", name, element, error),
+    theme_unknown_element: |name, element| format!("主题 {} 中未知的元素: {}", name, element),
+    color_unknown: |color| format!("无法识别的颜色: {}", color),
+
+    // Batch
+    batch_rate_invalid: "--rate 必须是非负数",
+    batch_input_error: |path, error| format!("无法读取输入文件 {}: {}", path, error),
+    batch_output_error: |path, error| format!("无法写入结果文件 {}: {}", path, error),
+    batch_results_error: |path, error| format!("无法读取结果文件 {}: {}", path, error),
+    batch_line: |line, error| format!("第 {} 行: {}", line, error),
+    batch_duplicate_id: |id| format!("重复的 id \"{}\"", id),
+    batch_invalid_request: |error| format!("无效的请求: {}", error),
+    batch_reserved_id: |prefix| format!("id 不能以 {} 开头", prefix),
+    batch_bad_id: "id 必须是字符串或数字",
+    batch_unknown_model: |model| format!("未知模型: {}", model),
+    batch_start: |total, path| format!("共 {} 个请求，结果写入 {}", total, path),
+    batch_resume: |skipped, total, path| format!("跳过 {} 个已有结果的请求，剩余 {} 个，结果追加到 {}", skipped, total, path),
+    batch_failed: |done, total, id, error| format!("[{}/{}] {} 失败: {}", done, total, id, error),
+    batch_succeeded: |done, total, id, ms| format!("[{}/{}] {} 完成 ({} ms)", done, total, id, ms),
+    batch_done: |succeeded, failed| format!("完成: 成功 {}，失败 {}", succeeded, failed),
+};
+
+static EN_US: Text = Text {
+    role_user: "You",
+    role_assistant: "AI",
+    role_system: "System",
+    role_summary: "Summary",
+
+    title_input: |send, config, help| format!("Input ({} send, {} config, {} help)", send, config, help),
+    title_history: "History (↑/↓ scroll)",
+    title_comparison: "Model comparison (←/→ select, ↑/↓ scroll, Enter adopt, Esc discard)",
+    title_help: "Help",
+    title_config: "Enter auth token (current token is saved)",
+    title_import: "Import conversation (file path, path#N for multi-conversation files)",
+    title_session_search: |count| format!("Search all sessions ({} sessions, ↑/↓ select, Enter open, Esc close)", count),
+    title_profile_select: |current| format!("Switch profile (current: {})", current),
+    title_compare_select: "Select models to compare (Space toggle, Enter confirm)",
+    title_model_select: |current| format!("Select model (current: {})", current),
+    title_sidebar: "Sessions",
+    sidebar_empty: "(no saved sessions)",
+    title_attach: "Attach file (path of an image or text file)",
+
+    help_heading: "Help:",
+    help_scroll: "Scroll the history pane",
+    help_quit: "Quit",
+    help_close_popup: "Close popup",
+    help_complete_reference: "Complete the @file reference at the end of the input",
+    help_vim_heading: "Vim mode (in normal mode):",
+    help_vim_scroll: "Scroll history down/up",
+    help_vim_jump: "Jump to start/end of history",
+    help_vim_half_page: "Scroll half a page down/up",
+    help_vim_search: "Search history",
+    help_vim_next: "Next/previous match",
+    help_vim_copy: "Copy the last AI reply",
+    help_vim_insert: "Enter insert mode (Esc to return)",
+    help_keybindings: "(Shortcuts can be changed under keybindings in the config file)",
+    action_help: "Show this help",
+    action_config: "Configure auth token",
+    action_select_model: "Select model",
+    action_copy_reply: "Copy the last AI reply",
+    action_import: "Import conversation (JSON/JSONL)",
+    action_compare_models: "Select models to compare (Space toggles, two or more enable comparison)",
+    action_switch_profile: "Switch profile",
+    action_search: "Search history (or / in the history pane, n/N for next/previous match)",
+    action_search_sessions: "Search all saved sessions",
+    action_attach: "Attach an image or text file",
+    action_cycle_layout: "Cycle layout (input on top/bottom/right)",
+    action_grow_input: "Enlarge the input pane",
+    action_shrink_input: "Shrink the input pane",
+    action_toggle_sidebar: "Show/hide the session sidebar",
+    action_zen_mode: "Zen mode (history only)",
+    action_switch_focus: "Switch between input and history",
+    action_send: "Send request",
+    action_quit: "Quit",
+
+    status_waiting: |frame, seconds| format!(" {} Waiting {:.1}s", frame, seconds),
+    status_requests: |count| format!(" · {} requests", count),
+    status_queued: |count| format!(" · {} queued", count),
+    status_retry: |attempt, max_attempts| format!(" · retry {}/{}", attempt, max_attempts),
+    status_retry_in: |seconds| format!(" (in {}s)", seconds),
+    status_command: |frame, command, seconds| format!(" {} Running {} {:.1}s", frame, command, seconds),
+    status_ready: " ● Ready",
+    status_compare: |models| format!("Compare: {}", models),
+    status_profile: |name| format!("Profile: {}", name),
+    status_session: |name| format!("Session: {}", name),
+    status_unsaved: "unsaved",
+    status_context: |tokens, limit| format!("Context: ~{}/{}", tokens, limit),
+    status_latency: |seconds| format!("Latency: {:.1}s", seconds),
+    status_usage: |tokens| format!("Usage: {} tokens", tokens),
+    usage_details: |input, output| format!(" · in {} / out {} tokens", input, output),
+
+    search_regex: "  [regex]",
+    search_case_sensitive: "  [case-sensitive]",
+    search_hint: "  (Ctrl+R regex, Ctrl+T case, Enter search, Esc cancel)",
+    search_no_match: |query| format!("Search \"{}\": no matches", query),
+    search_position: |query, current, count| format!("Search \"{}\": {}/{}", query, current, count),
+    search_count: |query, count| format!("Search \"{}\": {} matches", query, count),
+    invalid_regex: |error| format!("Invalid regular expression: {}", error),
+
+    session_skipped: |error| format!("Skipped: {}", error),
+    session_search_hint: "Type keywords to search all saved sessions",
+    session_search_empty: "No matching messages",
+    session_wait_pending: "Wait for the current request to finish before switching sessions",
+    session_missing: |name| format!("Session {} no longer exists", name),
+    session_load_error: |error| format!("Failed to load session: {}", error),
+    session_save_error: |error| format!("Failed to save session: {}", error),
+    session_loaded: |name, count, model| format!("Loaded session {} ({} messages), model: {}", name, count, model),
+    session_read_error: |path| format!("Cannot read session file {}", path),
+    session_format_error: |path| format!("Invalid session file {}", path),
+
+    waiting_response: "Waiting for response...",
+    reply_failed: " · failed",
+    reply_pending: " · waiting",
+    no_auth: "Configure an API auth token or AK/SK first",
+    error: |error| format!("Error: {}", error),
+    request_error: |error| format!("Request error: {}", error),
+    parse_error: |error| format!("Failed to parse response: {}", error),
+    api_error: |error| format!("API error: {}", error),
+    no_content: |text| format!("No content in response: {}", text),
+    unknown_error: "unknown error",
+    attempts: |error, attempts| format!("{} (after {} attempts)", error, attempts),
+    aksk_failed: "AK/SK authentication failed",
+    iam_url_invalid: |url| format!("Invalid IAM URL: {}", url),
+    no_iam_token: |text| format!("No token in response: {}", text),
+
+    marker_summarizing: |count| format!("⋯ The {} messages above exceed the context window and are being summarized ⋯", count),
+    marker_summarized: |count| format!("⋯ The {} messages above were condensed into the summary below ⋯", count),
+    marker_summary_failed: |count, error| format!("⋯ The {} messages above exceed the context window and are no longer sent (summary failed: {}) ⋯", count, error),
+    marker_truncated: |count| format!("⋯ The {} messages above exceed the context window and are no longer sent ⋯", count),
+
+    model_switched: |model| format!("Switched to model: {}", model),
+    compare_on: |models| format!("Comparison mode: {}", models),
+    compare_off: "Comparison mode off",
+    compare_adopted: |model| format!("Adopted the reply from {}; comparison mode is off and the conversation continues with this model", model),
+    compare_discarded: "Discarded this comparison and removed its prompt from the conversation",
+    profile_switched: |name, model, url| format!("Switched to profile: {}, model: {}, endpoint: {}", name, model, url),
+    profile_no_auth: "This profile has no auth token or AK/SK configured",
+    profile_unknown_model: |model| format!("The profile's model {} is not a known model; keeping the current model", model),
+    profile_switch_failed: |error| format!("Failed to switch profile: {}", error),
+    profile_current: "  [current]",
+    config_save_error: |error| format!("Failed to save config: {}", error),
+    layout_save_error: |error| format!("Failed to save layout: {}", error),
+    token_external: "The token comes from auth_token_env or auth_token_command; this change only lasts for the current run",
+    copied: "Copied to clipboard",
+    copy_failed: |error| format!("Failed to copy to clipboard: {}", error),
+
+    attachment_header: |name| format!("File {}:", name),
+    attachment_too_large: |name, limit| format!("{} exceeds the size limit of {}", name, limit),
+    attachment_binary: |name| format!("{} is not an image or a UTF-8 text file", name),
+    attach_failed: |error| format!("Failed to attach file: {}", error),
+    attachment_store_error: |path| format!("Failed to store the image: {}", path),
+    attach_remove_hint: " (Backspace on empty input removes)",
+    reference_failed: |error| format!("Could not expand an @ reference, the message was not sent: {}", error),
+    command_header: |command, code| match code {
+        Some(code) => format!("Output of `{}` (exit code {}):", command, code),
+        None => format!("Output of `{}` (killed by a signal):", command),
+    },
+    command_attached: |lines| format!("({} lines of output, all attached to the next message; Backspace on empty input removes it)", lines),
+    command_busy: "A command is already running, wait for it to finish",
+    command_failed: |command| format!("Failed to run command: {}", command),
+    command_timeout: |command, seconds| format!("Command {} did not finish within {} seconds and was killed", command, seconds),
+    command_truncated: "…(output too long, only the end is kept)",
+    vision_unsupported: |model| format!("{} does not accept images; switch to a vision model (more can be added under vision_models in the config)", model),
+
+    imported: |count, format| format!("Imported {} messages (format: {})", count, format),
+    imported_model: |model| format!(", original model: {}", model),
+    imported_continue: |model| format!(", continuing with {}", model),
+    imported_system_prompt: ", system prompt loaded",
+    import_failed: |error| format!("Import failed: {}", error),
+    import_format_chatgpt: "ChatGPT export",
+    import_no_path: "Enter the path of the file to import",
+    import_read_error: |path| format!("Cannot read file {}", path),
+    import_empty: |path| format!("File is empty: {}", path),
+    import_no_messages: "The file has no user or AI messages to import",
+    import_index_from_one: "Conversation numbers start at 1",
+    import_only_n: |count| format!("The file has only {} conversations", count),
+    import_picked: |count, index| format!("The file has {} conversations; imported number {} (use path#N to choose)", count, index),
+    import_bad_file: |error| format!("Invalid conversation file: {}", error),
+    import_messages_not_array: "the messages field must be an array",
+    import_unknown_format: "Unrecognized conversation format",
+    import_invalid_role: |index, role| format!("Message {} has an invalid role: {}", index, role),
+    import_invalid_json_line: |line, error| format!("Line {} is not valid JSON: {}", line, error),
+    import_line: |line| format!("line {}", line),
+    import_message: |index| format!("message {}", index),
+    import_line_message: |line, index| format!("{}, message {}", line, index),
+    import_mixed_jsonl: "The JSONL file mixes conversations and single messages",
+    import_missing_role: "missing role field",
+    import_unsupported_role: |role| format!("unsupported role \"{}\"", role),
+    import_missing_content: "missing content field",
+    import_bad_content: "the content field must be a string or an array of parts",
+    import_chatgpt_error: |error| format!("Invalid ChatGPT export: {}", error),
+    import_chatgpt_mapping: "the mapping field must be an object",
+    import_chatgpt_no_node: "current node not found",
+    import_chatgpt_cycle: "the nodes contain a cycle",
+    import_skipped: |count| format!("Skipped {} non-text or tool messages", count),
+    import_title: |title| format!("Conversation title: {}", title),
+
+    // Command line
+    cli_about: "Terminal UI for Baidu Qianfan large language models",
+    cli_prompt: "Prompt to send right after starting",
+    cli_model: "Model to use (default deepseek-r1)",
+    cli_config: "Path of the config file",
+    cli_endpoint: "API endpoint",
+    cli_profile: "Profile to use",
+    cli_session: "Session name: loaded at start and saved after every reply",
+    cli_ask: "Non-interactive mode: send one prompt and print the reply to stdout",
+    cli_ask_prompt: "The prompt; read from stdin when omitted or -",
+    cli_ask_stdin: "Read stdin and append it to the prompt",
+    cli_ask_system: "System prompt",
+    cli_ask_json: "Print the reply, usage and latency as JSON",
+    cli_ask_curl: "Only print the equivalent curl command (with the token as $TOKEN), without sending",
+    cli_batch: "Process the requests of a JSONL file and write the results to a JSONL file",
+    cli_batch_input: "Input file with one JSON request per line: {\"id\", \"prompt\", \"model\", \"system\", \"params\"}",
+    cli_batch_output: "Results file (default: <input name>.results.jsonl); requests that already succeeded in it are skipped",
+    cli_batch_concurrency: "Number of requests in flight at once",
+    cli_batch_rate: "Maximum requests started per second (0 for no limit)",
+    cli_token: "Manage the encrypted storage of the auth token and secret key",
+    cli_token_encrypt: "Encrypt the auth token and secret key in the config file with a passphrase",
+    cli_token_decrypt: "Decrypt the auth token and secret key and store them as plain text",
+    cli_no_auth: "Configure an API auth token or AK/SK first (run llm_tui and press Alt+C)",
+    cli_prompt_missing: "No prompt given (pass it as an argument or pipe it in)",
+    cli_prompt_empty: "The prompt is empty",
+    cli_stdin_error: |error| format!("Failed to read stdin: {}", error),
+    cli_retrying: |error, delay, attempt, max_attempts| format!("{}, retrying in {:.1}s ({}/{})", error, delay, attempt, max_attempts),
+    unknown_model: |models| format!("Unknown model, available models: {}", models),
+    endpoint_invalid: "The API endpoint must start with http:// or https://",
+    profile_not_found: |name, available| format!("Profile not found: {} (available: {})", name, available),
+    profile_invalid_model: |name, model, error| format!("Profile {} has an invalid model {}: {}", name, model, error),
+    session_name_empty: "The session name must not be empty",
+    session_name_invalid: |name| format!("The session name must not contain path separators or start with .: {}", name),
+    language_unsupported: |name| format!("Unsupported language: {} (available: zh-CN, en-US)", name),
+    clipboard_init_failed: "Failed to initialize the clipboard",
+
+    // Config file and data directories
+    config_not_found: |path| format!("Config file not found: {}", path),
+    config_read_error: |path| format!("Cannot read config file {}", path),
+    config_invalid: |path| format!("Invalid config file {}", path),
+    config_upgrade_error: |path| format!("Cannot write the upgraded config file {}", path),
+    config_backup_error: |path| format!("Cannot back up config file {}", path),
+    config_json_syntax: |error| format!("JSON syntax error: {}", error),
+    config_toml_syntax: |error| format!("TOML syntax error: {}", error),
+    config_toml_error: "Cannot convert to TOML",
+    config_field: |path, error| format!("field {}: {}", path, error),
+    config_not_object: "The top level of the config file must be an object",
+    config_bad_version: "field version: must be a non-negative integer",
+    config_too_new: |version, supported| format!("Config file version {} is newer than the supported version {}, please upgrade llm_tui", version, supported),
+    legacy_migrated: |from, to| format!("Moved the config and sessions from {} to {}", from, to),
+    legacy_migrate_failed: |error| format!("Warning: failed to migrate the old config directory: {}", error),
+    move_failed: |from, to| format!("Cannot move {} to {}", from, to),
+
+    // Credentials
+    token_env_unset: |var| format!("Environment variable {} is not set (auth_token_env)", var),
+    token_command_error: |command| format!("Cannot run the token command `{}`", command),
+    token_command_failed: |command, status| format!("The token command `{}` failed ({})", command, status),
+    token_command_empty: |command| format!("The token command `{}` printed nothing", command),
+    passphrase_prompt: "Token passphrase: ",
+    passphrase_new: "New token passphrase: ",
+    passphrase_repeat: "Repeat the passphrase: ",
+    passphrase_read_error: |var| format!("Cannot read the passphrase (it can be given in the environment variable {})", var),
+    passphrase_mismatch: "The passphrases do not match",
+    passphrase_empty: "The passphrase must not be empty",
+    encrypt_failed: "Failed to encrypt the token",
+    encrypted_token_invalid: "encrypted_token is malformed",
+    decrypt_failed: "Wrong passphrase or corrupted encrypted token",
+    encrypted_token_corrupt: "The encrypted token is corrupted",
+    key_derivation_failed: |error| format!("Key derivation failed: {}", error),
+    token_already_encrypted: "The auth token or secret key is already encrypted, decrypt first",
+    token_not_encrypted: "Neither the auth token nor the secret key is encrypted",
+    token_missing: "There is no auth token or secret key in the config file",
+    token_encrypted: |profile, path| format!("Encrypted the credentials of profile {} in {}", profile, path),
+    token_decrypted: |profile, path| format!("Stored the credentials of profile {} as plain text in {}", profile, path),
+
+    // Key bindings and themes
+    key_empty: "no key given",
+    key_unknown_modifier: |modifier| format!("unknown modifier: {}", modifier),
+    key_unknown: |key| format!("unknown key: {}", key),
+    key_reserved_quit: "is reserved for quitting",
+    key_reserved_typing: "would conflict with typing",
+    key_reserved_editing: "is used for editing and scrolling",
+    key_conflict: |key, first, second| format!("{} is bound to both {} and {}", key, first, second),
+    keybindings_invalid: |errors| format!("Invalid keybindings:\n  {}", errors),
+    theme_not_found: |name, available| format!("Theme not found: {} (available: {})", name, available),
+    theme_bad_base: |name, builtin| format!("The base of theme {} must be a built-in theme ({})", name, builtin),
+    theme_bad_style: |name, element, error| format!("{} of theme {}: {}", element, name, error),
+    theme_unknown_element: |name, element| format!("Unknown element in theme {}: {}", name, element),
+    color_unknown: |color| format!("unrecognized color: {}", color),
+
+    // Batch
+    batch_rate_invalid: "--rate must be a non-negative number",
+    batch_input_error: |path, error| format!("Cannot read input file {}: {}", path, error),
+    batch_output_error: |path, error| format!("Cannot write results file {}: {}", path, error),
+    batch_results_error: |path, error| format!("Cannot read results file {}: {}", path, error),
+    batch_line: |line, error| format!("line {}: {}", line, error),
+    batch_duplicate_id: |id| format!("duplicate id \"{}\"", id),
+    batch_invalid_request: |error| format!("invalid request: {}", error),
+    batch_reserved_id: |prefix| format!("the id must not start with {}", prefix),
+    batch_bad_id: "the id must be a string or a number",
+    batch_unknown_model: |model| format!("unknown model: {}", model),
+    batch_start: |total, path| format!("{} requests, writing results to {}", total, path),
+    batch_resume: |skipped, total, path| format!("Skipping {} requests that already have results, {} left, appending results to {}", skipped, total, path),
+    batch_failed: |done, total, id, error| format!("[{}/{}] {} failed: {}", done, total, id, error),
+    batch_succeeded: |done, total, id, ms| format!("[{}/{}] {} done ({} ms)", done, total, id, ms),
+    batch_done: |succeeded, failed| format!("Finished: {} succeeded, {} failed", succeeded, failed),
+};
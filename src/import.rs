@@ -0,0 +1,454 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::i18n;
+use crate::Message;
+
+/// The conversation file format written by this program.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Conversation {
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    pub messages: Vec<Message>,
+}
+
+pub enum ImportFormat {
+    Native,
+    // A bare JSON array of {role, content} objects
+    OpenAiArray,
+    // A JSON object with a `messages` array, like a chat completion request
+    OpenAiRequest,
+    OpenAiJsonl,
+    ChatGptExport,
+}
+
+impl ImportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImportFormat::Native => "llm_tui",
+            ImportFormat::OpenAiArray => "OpenAI JSON array",
+            ImportFormat::OpenAiRequest => "OpenAI JSON",
+            ImportFormat::OpenAiJsonl => "OpenAI JSONL",
+            ImportFormat::ChatGptExport => i18n::text().import_format_chatgpt,
+        }
+    }
+}
+
+pub struct ImportResult {
+    pub conversation: Conversation,
+    pub format: ImportFormat,
+    pub notes: Vec<String>,
+}
+
+/// Imports a conversation from `spec`, which is a file path optionally followed
+/// by `#N` to pick the N-th conversation (1-based) from multi-conversation files.
+pub fn import_conversation(spec: &str) -> Result<ImportResult> {
+    let (path, index) = parse_spec(spec.trim())?;
+    let contents = fs::read_to_string(&path)
+        .with_context(|| (i18n::text().import_read_error)(&path.display()))?;
+    if contents.trim().is_empty() {
+        bail!("{}", (i18n::text().import_empty)(&path.display()));
+    }
+
+    let mut result = match serde_json::from_str::<Value>(&contents) {
+        Ok(json) => import_json(json, index)?,
+        Err(_) => import_jsonl(&contents, index)?,
+    };
+
+    if !result.conversation.messages.iter().any(|m| m.role == "user" || m.role == "assistant") {
+        bail!("{}", i18n::text().import_no_messages);
+    }
+    if result.conversation.model.as_deref() == Some("") {
+        result.conversation.model = None;
+    }
+    Ok(result)
+}
+
+fn parse_spec(spec: &str) -> Result<(PathBuf, usize)> {
+    if spec.is_empty() {
+        bail!("{}", i18n::text().import_no_path);
+    }
+    let (path, index) = match spec.rsplit_once('#') {
+        Some((path, n)) if !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) => {
+            let n: usize = n.parse()?;
+            if n == 0 {
+                bail!("{}", i18n::text().import_index_from_one);
+            }
+            (path, n - 1)
+        }
+        _ => (spec, 0),
+    };
+    Ok((expand_tilde(path), index))
+}
+
+pub fn expand_tilde(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => Path::new(path).to_path_buf(),
+    }
+}
+
+fn import_json(json: Value, index: usize) -> Result<ImportResult> {
+    match json {
+        Value::Array(items) => {
+            if items.first().is_some_and(|item| item.get("mapping").is_some()) {
+                let count = items.len();
+                let item = items.into_iter().nth(index)
+                    .ok_or_else(|| anyhow!("{}", (i18n::text().import_only_n)(count)))?;
+                let mut result = import_chatgpt(&item)?;
+                if count > 1 {
+                    result.notes.push((i18n::text().import_picked)(count, index + 1));
+                }
+                Ok(result)
+            } else {
+                // A bare array of {role, content} objects
+                let messages = parse_openai_messages(&items, "")?;
+                Ok(openai_result(messages, None, ImportFormat::OpenAiArray, Vec::new()))
+            }
+        }
+        Value::Object(_) if json.get("mapping").is_some() => import_chatgpt(&json),
+        Value::Object(_) if json.get("messages").is_some() => {
+            if is_native(&json) {
+                let conversation: Conversation = serde_json::from_value(json)
+                    .map_err(|e| anyhow!("{}", (i18n::text().import_bad_file)(&e.to_string())))?;
+                validate_roles(&conversation.messages)?;
+                Ok(ImportResult { conversation, format: ImportFormat::Native, notes: Vec::new() })
+            } else {
+                let items = json["messages"].as_array()
+                    .ok_or_else(|| anyhow!("{}", i18n::text().import_messages_not_array))?;
+                let messages = parse_openai_messages(items, "")?;
+                let model = json["model"].as_str().map(str::to_string);
+                Ok(openai_result(messages, model, ImportFormat::OpenAiRequest, Vec::new()))
+            }
+        }
+        _ => bail!("{}", i18n::text().import_unknown_format),
+    }
+}
+
+fn is_native(json: &Value) -> bool {
+    json.get("system_prompt").is_some()
+        || json["messages"].as_array().is_some_and(|items| {
+            items.iter().all(|m| m.get("timestamp").is_some())
+        })
+}
+
+fn validate_roles(messages: &[Message]) -> Result<()> {
+    for (i, msg) in messages.iter().enumerate() {
+        if !matches!(msg.role.as_str(), "user" | "assistant" | "system") {
+            bail!("{}", (i18n::text().import_invalid_role)(i + 1, &msg.role));
+        }
+    }
+    Ok(())
+}
+
+fn import_jsonl(contents: &str, index: usize) -> Result<ImportResult> {
+    let mut conversations = Vec::new();
+    let mut loose_messages = Vec::new();
+
+    for (line_no, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let json: Value = serde_json::from_str(line)
+            .map_err(|e| anyhow!("{}", (i18n::text().import_invalid_json_line)(line_no + 1, &e.to_string())))?;
+        let location = (i18n::text().import_line)(line_no + 1);
+        if let Some(items) = json.get("messages") {
+            let items = items.as_array()
+                .ok_or_else(|| anyhow!("{}: {}", location, i18n::text().import_messages_not_array))?;
+            conversations.push(parse_openai_messages(items, &location)?);
+        } else {
+            loose_messages.extend(parse_openai_messages(std::slice::from_ref(&json), &location)?);
+        }
+    }
+
+    if !conversations.is_empty() && !loose_messages.is_empty() {
+        bail!("{}", i18n::text().import_mixed_jsonl);
+    }
+
+    let mut notes = Vec::new();
+    let messages = if conversations.is_empty() {
+        loose_messages
+    } else {
+        let count = conversations.len();
+        if count > 1 {
+            notes.push((i18n::text().import_picked)(count, index + 1));
+        }
+        conversations.into_iter().nth(index)
+            .ok_or_else(|| anyhow!("{}", (i18n::text().import_only_n)(count)))?
+    };
+    Ok(openai_result(messages, None, ImportFormat::OpenAiJsonl, notes))
+}
+
+struct RawMessage {
+    role: String,
+    content: String,
+}
+
+fn parse_openai_messages(items: &[Value], location: &str) -> Result<Vec<RawMessage>> {
+    let mut messages = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let location = if location.is_empty() {
+            (i18n::text().import_message)(i + 1)
+        } else if items.len() > 1 {
+            (i18n::text().import_line_message)(location, i + 1)
+        } else {
+            location.to_string()
+        };
+        let role = item["role"].as_str()
+            .ok_or_else(|| anyhow!("{}: {}", location, i18n::text().import_missing_role))?;
+        if !matches!(role, "user" | "assistant" | "system") {
+            bail!("{}: {}", location, (i18n::text().import_unsupported_role)(role));
+        }
+        let content = match &item["content"] {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts.iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Value::Null => bail!("{}: {}", location, i18n::text().import_missing_content),
+            _ => bail!("{}: {}", location, i18n::text().import_bad_content),
+        };
+        messages.push(RawMessage { role: role.to_string(), content });
+    }
+    Ok(messages)
+}
+
+fn openai_result(raw: Vec<RawMessage>, model: Option<String>, format: ImportFormat, notes: Vec<String>) -> ImportResult {
+    let mut system_prompt: Option<String> = None;
+    let mut messages = Vec::new();
+    for msg in raw {
+        if msg.role == "system" {
+            // System prompts are sent as context, not displayed as notices
+            match &mut system_prompt {
+                Some(prompt) => {
+                    prompt.push_str("\n\n");
+                    prompt.push_str(&msg.content);
+                }
+                None => system_prompt = Some(msg.content),
+            }
+            continue;
+        }
+        messages.push(Message::new(&msg.role, msg.content));
+    }
+    ImportResult {
+        conversation: Conversation { model, system_prompt, messages },
+        format,
+        notes,
+    }
+}
+
+fn import_chatgpt(conversation: &Value) -> Result<ImportResult> {
+    let mapping = conversation["mapping"].as_object()
+        .ok_or_else(|| anyhow!("{}", (i18n::text().import_chatgpt_error)(i18n::text().import_chatgpt_mapping)))?;
+
+    // Walk from the current node up to the root to recover the active branch
+    let mut node_id = conversation["current_node"].as_str()
+        .map(str::to_string)
+        .or_else(|| {
+            mapping.iter()
+                .find(|(_, node)| node["children"].as_array().map_or(true, |c| c.is_empty()))
+                .map(|(id, _)| id.clone())
+        })
+        .ok_or_else(|| anyhow!("{}", (i18n::text().import_chatgpt_error)(i18n::text().import_chatgpt_no_node)))?;
+
+    let mut nodes = Vec::new();
+    while let Some(node) = mapping.get(&node_id) {
+        nodes.push(node);
+        if nodes.len() > mapping.len() {
+            bail!("{}", (i18n::text().import_chatgpt_error)(i18n::text().import_chatgpt_cycle));
+        }
+        match node["parent"].as_str() {
+            Some(parent) => node_id = parent.to_string(),
+            None => break,
+        }
+    }
+    nodes.reverse();
+
+    let mut notes = Vec::new();
+    let mut system_prompt = None;
+    let mut messages = Vec::new();
+    let mut skipped = 0;
+    for node in nodes {
+        let message = &node["message"];
+        if message.is_null() {
+            continue;
+        }
+        let role = message["author"]["role"].as_str().unwrap_or_default();
+        let content = &message["content"];
+        let text = match content["content_type"].as_str() {
+            Some("text") | None => content["parts"].as_array()
+                .map(|parts| parts.iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+                .unwrap_or_default(),
+            Some(_) => {
+                skipped += 1;
+                continue;
+            }
+        };
+        if text.trim().is_empty() {
+            continue;
+        }
+        match role {
+            "user" | "assistant" => {
+                let mut msg = Message::new(role, text);
+                if let Some(time) = message["create_time"].as_f64()
+                    .and_then(|t| chrono::DateTime::from_timestamp(t as i64, 0))
+                {
+                    msg.timestamp = time.with_timezone(&chrono::Local).format("%H:%M:%S").to_string();
+                }
+                messages.push(msg);
+            }
+            "system" if system_prompt.is_none() => system_prompt = Some(text),
+            _ => skipped += 1,
+        }
+    }
+    if skipped > 0 {
+        notes.push((i18n::text().import_skipped)(skipped));
+    }
+    if let Some(title) = conversation["title"].as_str() {
+        notes.push((i18n::text().import_title)(title));
+    }
+
+    Ok(ImportResult {
+        conversation: Conversation {
+            model: conversation["default_model_slug"].as_str().map(str::to_string),
+            system_prompt,
+            messages,
+        },
+        format: ImportFormat::ChatGptExport,
+        notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn roles(result: &ImportResult) -> Vec<(&str, &str)> {
+        result.conversation.messages.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect()
+    }
+
+    #[test]
+    fn spec_with_index() {
+        let (path, index) = parse_spec("chats.json#3").unwrap();
+        assert_eq!(path, PathBuf::from("chats.json"));
+        assert_eq!(index, 2);
+        assert!(parse_spec("chats.json#0").is_err());
+        // Only a trailing number is an index
+        let (path, index) = parse_spec("a#b.json").unwrap();
+        assert_eq!(path, PathBuf::from("a#b.json"));
+        assert_eq!(index, 0);
+    }
+
+    #[test]
+    fn openai_array() {
+        let json = json!([
+            { "role": "system", "content": "be brief" },
+            { "role": "user", "content": "hi" },
+            { "role": "assistant", "content": [{ "type": "text", "text": "hello" }, { "type": "text", "text": "there" }] },
+        ]);
+        let result = import_json(json, 0).unwrap();
+        assert!(matches!(result.format, ImportFormat::OpenAiArray));
+        assert_eq!(result.conversation.system_prompt.as_deref(), Some("be brief"));
+        assert_eq!(roles(&result), [("user", "hi"), ("assistant", "hello\nthere")]);
+    }
+
+    #[test]
+    fn openai_request_object() {
+        let json = json!({ "model": "gpt-4o", "messages": [{ "role": "user", "content": "hi" }] });
+        let result = import_json(json, 0).unwrap();
+        assert!(matches!(result.format, ImportFormat::OpenAiRequest));
+        assert_eq!(result.conversation.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(roles(&result), [("user", "hi")]);
+    }
+
+    #[test]
+    fn native_file() {
+        let json = json!({
+            "model": "ernie-3.5-8k",
+            "system_prompt": null,
+            "messages": [{ "role": "user", "content": "hi", "timestamp": "10:00:00" }],
+        });
+        let result = import_json(json, 0).unwrap();
+        assert!(matches!(result.format, ImportFormat::Native));
+        assert_eq!(result.conversation.messages[0].timestamp, "10:00:00");
+    }
+
+    #[test]
+    fn rejects_unknown_roles() {
+        assert!(import_json(json!([{ "role": "tool", "content": "x" }]), 0).is_err());
+        assert!(import_json(json!([{ "content": "x" }]), 0).is_err());
+        assert!(import_json(json!([{ "role": "user" }]), 0).is_err());
+    }
+
+    #[test]
+    fn jsonl_loose_messages() {
+        let contents = "{\"role\":\"user\",\"content\":\"a\"}\n\n{\"role\":\"assistant\",\"content\":\"b\"}\n";
+        let result = import_jsonl(contents, 0).unwrap();
+        assert!(matches!(result.format, ImportFormat::OpenAiJsonl));
+        assert_eq!(roles(&result), [("user", "a"), ("assistant", "b")]);
+    }
+
+    #[test]
+    fn jsonl_conversations() {
+        let contents = "{\"messages\":[{\"role\":\"user\",\"content\":\"first\"}]}\n{\"messages\":[{\"role\":\"user\",\"content\":\"second\"}]}";
+        let result = import_jsonl(contents, 1).unwrap();
+        assert_eq!(roles(&result), [("user", "second")]);
+        assert_eq!(result.notes.len(), 1);
+        assert!(import_jsonl(contents, 2).is_err());
+    }
+
+    #[test]
+    fn jsonl_rejects_mixed_and_invalid_lines() {
+        assert!(import_jsonl("{\"messages\":[]}\n{\"role\":\"user\",\"content\":\"a\"}", 0).is_err());
+        assert!(import_jsonl("{\"role\":\"user\",\"content\":\"a\"}\nnot json", 0).is_err());
+    }
+
+    #[test]
+    fn chatgpt_follows_current_branch() {
+        let json = json!({
+            "title": "Rust",
+            "default_model_slug": "gpt-4",
+            "current_node": "c",
+            "mapping": {
+                "root": { "parent": null, "children": ["a"], "message": null },
+                "a": { "parent": "root", "children": ["b", "old"], "message": {
+                    "author": { "role": "user" }, "content": { "content_type": "text", "parts": ["question"] } } },
+                "old": { "parent": "a", "children": [], "message": {
+                    "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["discarded"] } } },
+                "b": { "parent": "a", "children": ["c"], "message": {
+                    "author": { "role": "assistant" }, "content": { "content_type": "text", "parts": ["answer"] } } },
+                "c": { "parent": "b", "children": [], "message": {
+                    "author": { "role": "tool" }, "content": { "content_type": "code", "text": "print()" } } },
+            },
+        });
+        let result = import_json(json, 0).unwrap();
+        assert!(matches!(result.format, ImportFormat::ChatGptExport));
+        assert_eq!(result.conversation.model.as_deref(), Some("gpt-4"));
+        assert_eq!(roles(&result), [("user", "question"), ("assistant", "answer")]);
+        // One note for the skipped tool message, one for the title
+        assert_eq!(result.notes.len(), 2);
+    }
+
+    #[test]
+    fn chatgpt_detects_cycles() {
+        let json = json!({
+            "current_node": "a",
+            "mapping": {
+                "a": { "parent": "b", "message": null },
+                "b": { "parent": "a", "message": null },
+            },
+        });
+        assert!(import_json(json, 0).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::{anyhow, bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use crate::i18n;
+
+/// Commands that can be bound to keys via `keybindings` in the config.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Help,
+    Config,
+    SelectModel,
+    CopyReply,
+    Import,
+    CompareModels,
+    SwitchProfile,
+    Search,
+    SearchSessions,
+    Attach,
+    CycleLayout,
+    GrowInput,
+    ShrinkInput,
+    ToggleSidebar,
+    ZenMode,
+    SwitchFocus,
+    Send,
+    Quit,
+}
+
+impl Action {
+    // In the order shown in the help menu
+    pub const ALL: [Action; 18] = [
+        Action::Help,
+        Action::Config,
+        Action::SelectModel,
+        Action::CopyReply,
+        Action::Import,
+        Action::CompareModels,
+        Action::SwitchProfile,
+        Action::Search,
+        Action::SearchSessions,
+        Action::Attach,
+        Action::CycleLayout,
+        Action::GrowInput,
+        Action::ShrinkInput,
+        Action::ToggleSidebar,
+        Action::ZenMode,
+        Action::SwitchFocus,
+        Action::Send,
+        Action::Quit,
+    ];
+
+    fn default_key(self) -> &'static str {
+        match self {
+            Action::Help => "alt+h",
+            Action::Config => "alt+c",
+            Action::SelectModel => "alt+m",
+            Action::CopyReply => "alt+y",
+            Action::Import => "alt+i",
+            Action::CompareModels => "alt+p",
+            Action::SwitchProfile => "alt+a",
+            Action::Search => "ctrl+f",
+            Action::SearchSessions => "alt+s",
+            Action::Attach => "alt+f",
+            Action::CycleLayout => "alt+l",
+            Action::GrowInput => "alt+=",
+            Action::ShrinkInput => "alt+-",
+            Action::ToggleSidebar => "alt+b",
+            Action::ZenMode => "alt+z",
+            Action::SwitchFocus => "tab",
+            Action::Send => "enter",
+            Action::Quit => "esc",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        let text = i18n::text();
+        match self {
+            Action::Help => text.action_help,
+            Action::Config => text.action_config,
+            Action::SelectModel => text.action_select_model,
+            Action::CopyReply => text.action_copy_reply,
+            Action::Import => text.action_import,
+            Action::CompareModels => text.action_compare_models,
+            Action::SwitchProfile => text.action_switch_profile,
+            Action::Search => text.action_search,
+            Action::SearchSessions => text.action_search_sessions,
+            Action::Attach => text.action_attach,
+            Action::CycleLayout => text.action_cycle_layout,
+            Action::GrowInput => text.action_grow_input,
+            Action::ShrinkInput => text.action_shrink_input,
+            Action::ToggleSidebar => text.action_toggle_sidebar,
+            Action::ZenMode => text.action_zen_mode,
+            Action::SwitchFocus => text.action_switch_focus,
+            Action::Send => text.action_send,
+            Action::Quit => text.action_quit,
+        }
+    }
+
+    fn name(self) -> String {
+        serde_json::to_value(self).ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyBinding {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl KeyBinding {
+    /// Parses bindings such as `alt+h`, `ctrl+shift+k`, `f2` or `tab`.
+    pub fn parse(text: &str) -> Result<KeyBinding> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty())
+            .ok_or_else(|| anyhow!(i18n::text().key_empty))?;
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!((i18n::text().key_unknown_modifier)(part)),
+            };
+        }
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "esc" | "escape" => KeyCode::Esc,
+            "space" => KeyCode::Char(' '),
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            lower => match lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => {
+                    let mut chars = key.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => KeyCode::Char(c),
+                        _ => bail!((i18n::text().key_unknown)(key)),
+                    }
+                }
+            },
+        };
+        Ok(KeyBinding { code, modifiers }.normalized())
+    }
+
+    fn from_event(key: &KeyEvent) -> KeyBinding {
+        KeyBinding { code: key.code, modifiers: key.modifiers }.normalized()
+    }
+
+    // Terminals report Shift+letter as an uppercase char, with or without SHIFT
+    fn normalized(mut self) -> KeyBinding {
+        if let KeyCode::Char(c) = self.code {
+            if c.is_uppercase() {
+                self.code = KeyCode::Char(c.to_lowercase().next().unwrap_or(c));
+                self.modifiers |= KeyModifiers::SHIFT;
+            }
+        } else if self.code == KeyCode::BackTab {
+            self.code = KeyCode::Tab;
+            self.modifiers |= KeyModifiers::SHIFT;
+        }
+        self.modifiers &= KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT;
+        self
+    }
+
+    // Keys the main loop handles itself: quitting, typing and scrolling
+    fn reserved(&self) -> Option<&'static str> {
+        let text = i18n::text();
+        let plain = self.modifiers - KeyModifiers::SHIFT == KeyModifiers::NONE;
+        match self.code {
+            KeyCode::Char('c') if self.modifiers == KeyModifiers::CONTROL => Some(text.key_reserved_quit),
+            KeyCode::Char(_) if plain => Some(text.key_reserved_typing),
+            KeyCode::Backspace | KeyCode::Up | KeyCode::Down if plain => Some(text.key_reserved_editing),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c.to_uppercase()),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Delete"),
+            KeyCode::Insert => write!(f, "Insert"),
+            KeyCode::Home => write!(f, "Home"),
+            KeyCode::End => write!(f, "End"),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+pub struct Keymap {
+    bindings: HashMap<KeyBinding, Action>,
+    keys: HashMap<Action, KeyBinding>,
+    // Actions given a key in the config rather than by default
+    explicit: HashSet<Action>,
+}
+
+impl Keymap {
+    /// Builds the keymap from the defaults and the `keybindings` overrides,
+    /// rejecting unparsable keys, reserved keys and keys bound twice.
+    pub fn new(overrides: &BTreeMap<Action, String>) -> Result<Keymap> {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+            keys: HashMap::new(),
+            explicit: overrides.keys().copied().collect(),
+        };
+        let mut errors = Vec::new();
+        for action in Action::ALL {
+            let text = overrides.get(&action).map_or(action.default_key(), String::as_str);
+            let key = match KeyBinding::parse(text) {
+                Ok(key) => key,
+                Err(e) => {
+                    errors.push(format!("{} = \"{}\": {}", action.name(), text, e));
+                    continue;
+                }
+            };
+            if let Some(reason) = key.reserved() {
+                errors.push(format!("{} = \"{}\": {} {}", action.name(), text, key, reason));
+                continue;
+            }
+            if let Some(other) = keymap.bindings.insert(key, action) {
+                errors.push((i18n::text().key_conflict)(&key, &other.name(), &action.name()));
+            }
+            keymap.keys.insert(action, key);
+        }
+        if !errors.is_empty() {
+            bail!((i18n::text().keybindings_invalid)(&errors.join("\n  ")));
+        }
+        Ok(keymap)
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<Action> {
+        self.bindings.get(&KeyBinding::from_event(key)).copied()
+    }
+
+    /// Whether the key of `action` was set in the config.
+    pub fn is_explicit(&self, action: Action) -> bool {
+        self.explicit.contains(&action)
+    }
+
+    /// Display label for the key bound to `action`, e.g. `Alt+H`.
+    pub fn label(&self, action: Action) -> String {
+        self.keys.get(&action).map(ToString::to_string).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn label(text: &str) -> String {
+        KeyBinding::parse(text).unwrap().to_string()
+    }
+
+    #[test]
+    fn parses_modifiers_and_named_keys() {
+        assert_eq!(label("alt+h"), "Alt+H");
+        assert_eq!(label("Ctrl + Shift + k"), "Ctrl+Shift+K");
+        assert_eq!(label("meta+space"), "Alt+Space");
+        assert_eq!(label("F12"), "F12");
+        assert_eq!(label("escape"), "Esc");
+        assert_eq!(label("alt+="), "Alt+=");
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(KeyBinding::parse("").is_err());
+        assert!(KeyBinding::parse("alt+").is_err());
+        assert!(KeyBinding::parse("hyper+h").is_err());
+        assert!(KeyBinding::parse("f13").is_err());
+        assert!(KeyBinding::parse("alt+hh").is_err());
+    }
+
+    #[test]
+    fn normalizes_uppercase_and_backtab() {
+        assert!(KeyBinding::parse("alt+H").unwrap() == KeyBinding::parse("alt+shift+h").unwrap());
+        let keymap = Keymap::new(&BTreeMap::from([(Action::Help, "shift+tab".to_string())])).unwrap();
+        assert!(keymap.action(&key(KeyCode::BackTab, KeyModifiers::SHIFT)) == Some(Action::Help));
+        assert!(keymap.action(&key(KeyCode::Char('H'), KeyModifiers::ALT)).is_none());
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let keymap = Keymap::new(&BTreeMap::new()).unwrap();
+        assert!(keymap.action(&key(KeyCode::Char('h'), KeyModifiers::ALT)) == Some(Action::Help));
+        assert!(keymap.action(&key(KeyCode::Enter, KeyModifiers::NONE)) == Some(Action::Send));
+        assert_eq!(keymap.label(Action::Search), "Ctrl+F");
+        assert!(!keymap.is_explicit(Action::Quit));
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let keymap = Keymap::new(&BTreeMap::from([(Action::Quit, "ctrl+q".to_string())])).unwrap();
+        assert!(keymap.action(&key(KeyCode::Char('q'), KeyModifiers::CONTROL)) == Some(Action::Quit));
+        assert!(keymap.action(&key(KeyCode::Esc, KeyModifiers::NONE)).is_none());
+        assert!(keymap.is_explicit(Action::Quit));
+    }
+
+    #[test]
+    fn rejects_conflicts_and_reserved_keys() {
+        let conflict = BTreeMap::from([(Action::Help, "alt+c".to_string())]);
+        assert!(Keymap::new(&conflict).is_err());
+        for reserved in ["ctrl+c", "x", "shift+x", "up", "backspace"] {
+            let overrides = BTreeMap::from([(Action::Help, reserved.to_string())]);
+            assert!(Keymap::new(&overrides).is_err(), "{}", reserved);
+        }
+        let overrides = BTreeMap::from([(Action::Help, "ctrl+up".to_string())]);
+        assert!(Keymap::new(&overrides).is_ok());
+    }
+}
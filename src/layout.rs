@@ -0,0 +1,173 @@
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::text::Span;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::paths;
+
+const DEFAULT_INPUT_SIZE: u16 = 30;
+const MIN_INPUT_SIZE: u16 = 10;
+const MAX_INPUT_SIZE: u16 = 80;
+const RESIZE_STEP: u16 = 5;
+const SIDEBAR_WIDTH: u16 = 24;
+
+/// Where the input pane goes relative to the history.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutStyle {
+    #[default]
+    Top,
+    // Grows with the input, up to `input_size` percent of the height
+    Bottom,
+    // Input to the right of the history
+    Horizontal,
+}
+
+impl LayoutStyle {
+    pub fn next(self) -> LayoutStyle {
+        match self {
+            LayoutStyle::Top => LayoutStyle::Bottom,
+            LayoutStyle::Bottom => LayoutStyle::Horizontal,
+            LayoutStyle::Horizontal => LayoutStyle::Top,
+        }
+    }
+}
+
+/// The `layout` section of the config.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LayoutConfig {
+    #[serde(default)]
+    pub style: LayoutStyle,
+    // Percent of the screen given to the input pane
+    #[serde(default = "default_input_size")]
+    pub input_size: u16,
+    // Show the list of saved sessions on the left
+    #[serde(default)]
+    pub sidebar: bool,
+}
+
+fn default_input_size() -> u16 {
+    DEFAULT_INPUT_SIZE
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        LayoutConfig {
+            style: LayoutStyle::default(),
+            input_size: DEFAULT_INPUT_SIZE,
+            sidebar: false,
+        }
+    }
+}
+
+impl LayoutConfig {
+    pub fn resize(&mut self, grow: bool) {
+        let size = if grow {
+            self.input_size.saturating_add(RESIZE_STEP)
+        } else {
+            self.input_size.saturating_sub(RESIZE_STEP)
+        };
+        self.input_size = size.clamp(MIN_INPUT_SIZE, MAX_INPUT_SIZE);
+    }
+}
+
+fn state_path() -> PathBuf {
+    paths::state_dir().join("layout.json")
+}
+
+/// The layout last set with the keys, which takes precedence over the config.
+pub fn load_state() -> Option<LayoutConfig> {
+    let contents = fs::read_to_string(state_path()).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+/// Saves the layout to the state dir, leaving the config file untouched.
+pub fn save_state(layout: &LayoutConfig) -> Result<()> {
+    let path = state_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(layout)?)?;
+    Ok(())
+}
+
+/// Screen areas of the panes. Hidden panes are `None`.
+#[derive(Clone, Copy)]
+pub struct Areas {
+    pub input: Option<Rect>,
+    // Attachment chips, on the line above the input
+    pub chips: Option<Rect>,
+    pub history: Rect,
+    pub sidebar: Option<Rect>,
+    pub status: Option<Rect>,
+}
+
+/// Splits `area` into panes. Zen mode gives everything to the history.
+pub fn split(area: Rect, config: &LayoutConfig, zen: bool, input: &str, chips: bool) -> Areas {
+    if zen {
+        return Areas { input: None, chips: None, history: area, sidebar: None, status: None };
+    }
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(area);
+    let (mut main, status) = (rows[0], rows[1]);
+
+    let mut sidebar = None;
+    if config.sidebar {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(SIDEBAR_WIDTH), Constraint::Min(20)])
+            .split(main);
+        sidebar = Some(columns[0]);
+        main = columns[1];
+    }
+
+    let size = config.input_size.clamp(MIN_INPUT_SIZE, MAX_INPUT_SIZE);
+    let (input, history) = match config.style {
+        LayoutStyle::Top => {
+            let panes = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(size), Constraint::Min(3)])
+                .split(main);
+            (panes[0], panes[1])
+        }
+        LayoutStyle::Bottom => {
+            let max_height = (main.height as u32 * size as u32 / 100) as u16;
+            let height = input_height(input, main.width).min(max_height).max(3) + chips as u16;
+            let panes = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Min(3), Constraint::Length(height)])
+                .split(main);
+            (panes[1], panes[0])
+        }
+        LayoutStyle::Horizontal => {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(100 - size), Constraint::Percentage(size)])
+                .split(main);
+            (panes[1], panes[0])
+        }
+    };
+    let (input, chips) = if chips && input.height > 3 {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(2)])
+            .split(input);
+        (rows[1], Some(rows[0]))
+    } else {
+        (input, None)
+    };
+    Areas { input: Some(input), chips, history, sidebar, status: Some(status) }
+}
+
+/// Height of the bordered input pane showing all of `input` wrapped to `width`.
+pub fn input_height(input: &str, width: u16) -> u16 {
+    let inner = width.saturating_sub(2).max(1) as usize;
+    let lines: usize = input.split('\n')
+        .map(|line| (Span::raw(line).width() + inner) / inner)
+        .sum();
+    (lines as u16).saturating_add(2)
+}
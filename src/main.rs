@@ -1,23 +1,46 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseEvent, MouseEventKind, MouseButton},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, Paragraph, Clear},
+    widgets::{Block, Borders, Paragraph, Clear, Wrap},
 };
 use serde_json::Value;
-use std::{io, time::Duration, fmt::Write};
+use std::{io, time::{Duration, Instant}, fmt::Write};
 use std::fs;
 use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
 use copypasta::{ClipboardContext, ClipboardProvider};
 use tokio::sync::mpsc;
 use pulldown_cmark::{Parser, Event as MarkdownEvent, Tag};
 
-const AVAILABLE_MODELS: [&str; 21] = [
+mod api;
+mod attachment;
+mod auth;
+mod batch;
+mod cli;
+mod config;
+mod context;
+mod credentials;
+mod i18n;
+mod import;
+mod keymap;
+mod layout;
+mod paths;
+mod reference;
+mod requests;
+mod search;
+mod session;
+mod shell;
+mod theme;
+
+const DEFAULT_MODEL: &str = "deepseek-r1";
+
+const AVAILABLE_MODELS: [&str; 22] = [
     "ernie-4.0-8k-latest",
     "ernie-4.0-8k-preview",
     "ernie-4.0-8k",
@@ -37,55 +60,318 @@ const AVAILABLE_MODELS: [&str; 21] = [
     "ernie-char-8k",
     "ernie-char-fiction-8k",
     "ernie-novel-8k",
+    "ernie-4.5-turbo-vl-32k",
     "deepseek-v3",
     "deepseek-r1"
 ];
 
-#[derive(Clone)]
+// Lines of command output shown in the history
+const COMMAND_PREVIEW_LINES: usize = 5;
+
+// Models that accept image_url parts; more can be added with `vision_models`
+const VISION_MODELS: [&str; 1] = ["ernie-4.5-turbo-vl-32k"];
+
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MessageKind {
+    #[default]
+    Normal,
+    // Messages before this marker are no longer sent as context
+    ContextMarker,
+    // Summary of the messages before the context marker, sent as context
+    Summary,
+    // Placeholder for a reply that has not arrived yet, never saved
+    Pending,
+}
+
+impl MessageKind {
+    fn is_normal(&self) -> bool {
+        *self == MessageKind::Normal
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct Message {
     role: String,
     content: String,
     timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    usage: Option<api::Usage>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    latency_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "MessageKind::is_normal")]
+    kind: MessageKind,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<attachment::Attachment>,
+    // Request that produced this message, or that a pending placeholder waits for
+    #[serde(skip)]
+    request_id: Option<u64>,
+}
+
+// Messages of the history pane. Every mutable access bumps `generation`, so
+// state derived from the history can tell when it is stale.
+#[derive(Default)]
+struct History {
+    messages: Vec<Message>,
+    generation: u64,
+}
+
+impl std::ops::Deref for History {
+    type Target = Vec<Message>;
+
+    fn deref(&self) -> &Vec<Message> {
+        &self.messages
+    }
+}
+
+impl std::ops::DerefMut for History {
+    fn deref_mut(&mut self) -> &mut Vec<Message> {
+        self.generation += 1;
+        &mut self.messages
+    }
 }
 
 impl Message {
+    fn new(role: &str, content: impl Into<String>) -> Message {
+        Message {
+            role: role.to_string(),
+            content: content.into(),
+            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+            model: None,
+            usage: None,
+            latency_ms: None,
+            kind: MessageKind::Normal,
+            attachments: Vec::new(),
+            request_id: None,
+        }
+    }
+
+    // `content` as sent in a request, including attachments
+    fn request_content(&self) -> Value {
+        attachment::request_content(&self.content, &self.attachments)
+    }
+
     fn format_content(&self) -> String {
         // Simply return the content without any filtering
         self.content.clone()
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
-struct Config {
+// Prices in CNY per 1K tokens
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+struct ModelPrice {
+    #[serde(default)]
+    input: f64,
+    #[serde(default)]
+    output: f64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+const DEFAULT_PROFILE: &str = "default";
+
+// Credentials, endpoint and request defaults of one account. The top level of
+// the config file is the "default" profile; others live under "profiles".
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct Profile {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     auth_token: String,
+    // Read the token from this environment variable instead
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth_token_env: Option<String>,
+    // Use the first line printed by this command as the token, e.g. "pass show qianfan"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth_token_command: Option<String>,
+    // Passphrase-encrypted token, used instead of auth_token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_token: Option<credentials::EncryptedToken>,
+    // Baidu IAM access key / secret key, exchanged for bearer tokens when no token is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    access_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    secret_key: Option<String>,
+    // Passphrase-encrypted secret key, used instead of secret_key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    encrypted_secret_key: Option<credentials::EncryptedToken>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    iam_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    endpoint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    // Extra request body fields, e.g. temperature
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    params: serde_json::Map<String, Value>,
+}
+
+impl Profile {
+    // The secret key comes from credentials::resolve, which decrypts it if needed
+    fn auth(&self, token: String, secret_key: Option<&str>) -> auth::Auth {
+        auth::Auth::new(token, self.access_key.as_deref(), secret_key, self.iam_url.as_deref())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Config {
+    #[serde(default)]
+    version: u32,
+    #[serde(flatten)]
+    default_profile: Profile,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<String, Profile>,
+    // Profile used when --profile is not given and none was picked in the
+    // switcher; the switcher's choice is kept in the state dir
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    active_profile: Option<String>,
+    // Retries for timeouts, 5xx responses and rate limits
+    #[serde(default = "default_max_retries")]
+    max_retries: u32,
+    // Hold prompts sent while a request is pending and send them in order
+    #[serde(default)]
+    queue_requests: bool,
+    // Vim-style normal/insert modes for the input and history panes
+    #[serde(default)]
+    vim_mode: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pricing: HashMap<String, ModelPrice>,
+    #[serde(default)]
+    context_strategy: context::ContextStrategy,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    context_limits: HashMap<String, u64>,
+    // Overrides of the default shortcuts, e.g. {"help": "f1"}
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    keybindings: BTreeMap<keymap::Action, String>,
+    #[serde(default)]
+    layout: layout::LayoutConfig,
+    // Models besides VISION_MODELS that accept images
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    vision_models: Vec<String>,
+    // UI language, zh-CN or en-US; taken from LANG when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    theme: Option<String>,
+    // User themes, selectable by name through `theme`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    themes: BTreeMap<String, theme::ThemeConfig>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: config::CONFIG_VERSION,
+            default_profile: Profile::default(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            max_retries: default_max_retries(),
+            queue_requests: false,
+            vim_mode: false,
+            pricing: HashMap::new(),
+            context_strategy: context::ContextStrategy::default(),
+            context_limits: HashMap::new(),
+            keybindings: BTreeMap::new(),
+            layout: layout::LayoutConfig::default(),
+            vision_models: Vec::new(),
+            language: None,
+            theme: None,
+            themes: BTreeMap::new(),
+            path: PathBuf::new(),
+        }
+    }
 }
 
 impl Config {
-    fn load() -> Self {
-        let config_path = get_config_path();
-        if let Ok(contents) = fs::read_to_string(config_path) {
-            serde_json::from_str(&contents).unwrap_or_default()
+    // A file that fails to parse is an error rather than an empty config, so
+    // a typo can't lead to the token being overwritten
+    fn load(config_path: PathBuf) -> Result<Self> {
+        let contents = match fs::read_to_string(&config_path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Config { path: config_path, ..Config::default() });
+            }
+            Err(e) => return Err(e).with_context(|| (i18n::text().config_read_error)(&config_path.display())),
+        };
+        let (mut config, migrated): (Config, bool) = config::parse(&contents, config::Format::of(&config_path))
+            .with_context(|| (i18n::text().config_invalid)(&config_path.display()))?;
+        config.path = config_path;
+        if migrated {
+            config::write_migrated(&config.path, &config)
+                .with_context(|| (i18n::text().config_upgrade_error)(&config.path.display()))?;
+        }
+        Ok(config)
+    }
+
+    fn profile(&self, name: &str) -> Option<&Profile> {
+        if name == DEFAULT_PROFILE {
+            Some(&self.default_profile)
         } else {
-            Config::default()
+            self.profiles.get(name)
         }
     }
 
-    fn save(&self) -> Result<()> {
-        let config_path = get_config_path();
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
+    fn profile_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        if name == DEFAULT_PROFILE {
+            Some(&mut self.default_profile)
+        } else {
+            self.profiles.get_mut(name)
         }
-        let contents = serde_json::to_string(self)?;
-        fs::write(config_path, contents)?;
-        Ok(())
     }
+
+    fn profile_names(&self) -> Vec<String> {
+        std::iter::once(DEFAULT_PROFILE.to_string())
+            .chain(self.profiles.keys().filter(|name| *name != DEFAULT_PROFILE).cloned())
+            .collect()
+    }
+
+    fn accepts_images(&self, model: &str) -> bool {
+        VISION_MODELS.contains(&model) || self.vision_models.iter().any(|m| m == model)
+    }
+
+    fn context_limit(&self, model: &str) -> u64 {
+        self.context_limits.get(model).copied()
+            .unwrap_or_else(|| context::context_limit(model))
+    }
+
+    fn estimate_cost(&self, model: &str, usage: &api::Usage) -> Option<f64> {
+        let price = self.pricing.get(model)?;
+        Some((usage.prompt_tokens as f64 * price.input + usage.completion_tokens as f64 * price.output) / 1000.0)
+    }
+
+    fn save(&self) -> Result<()> {
+        config::write(&self.path, self)
+    }
+}
+
+enum ResponseEvent {
+    Reply { id: u64, message: Message },
+    // Result of summarizing the turns before the context marker with this id
+    Summary { id: u64, summary: Result<String, String> },
+    // A failed attempt is about to be retried
+    Retry { id: u64, attempt: u32, max_attempts: u32, delay: Duration },
+    // Output of a `!cmd` shell command
+    Command(Result<attachment::Attachment, String>),
 }
 
-fn get_config_path() -> PathBuf {
-    let mut path = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
-    path.push("llm_tui");
-    path.push("config.json");
-    path
+// Repeated Tab cycles through the paths matching an `@` reference
+struct ReferenceCompletion {
+    // Input up to and including the `@`
+    stem: String,
+    candidates: Vec<String>,
+    index: usize,
+}
+
+// A request that has been sent and not answered yet
+struct PendingRequest {
+    id: u64,
+    started: Instant,
+    // Attempt number, max attempts and scheduled time of the pending retry
+    retry: Option<(u32, u32, Instant)>,
 }
 
 struct App {
@@ -93,68 +379,208 @@ struct App {
     response: String,
     api_url: String,
     auth_token: String,
+    auth: auth::Auth,
     show_config: bool,
     show_help: bool,
     config_input: String,
-    visible_token: String,
-    active_box: usize, // 0: input, 1: response
-    history: Vec<Message>,
+    token_source: credentials::TokenSource,
+    // Passphrase of the encrypted token store, if that is where the token came from
+    passphrase: Option<String>,
+    secret_key: Option<String>,
+    active_box: usize, // 0: input, 1: response, 2: sidebar
+    history: History,
     config: Config,
     scroll_offset: u16,  // Add this for scrolling
     clipboard: ClipboardContext,
     response_area: Option<Rect>,  // Add this field
-    is_loading: bool,
-    tx: mpsc::Sender<Message>,
-    rx: mpsc::Receiver<Message>,
+    tx: mpsc::Sender<ResponseEvent>,
+    rx: mpsc::Receiver<ResponseEvent>,
     input_history: Vec<String>,
     input_history_index: Option<usize>,
     current_input: String,  // Store current input when navigating history
     current_model: String,
     show_model_select: bool,
     model_select_index: usize,
+    show_import: bool,
+    import_input: String,
+    show_compare_select: bool,
+    compare_select_index: usize,
+    // Models a prompt is sent to in compare mode (compare mode is off below two)
+    compare_models: Vec<String>,
+    comparison: Option<requests::Comparison>,
+    system_prompt: Option<String>,
+    session_name: Option<String>,
+    profile_name: String,
+    keymap: keymap::Keymap,
+    theme: theme::Theme,
+    // Only used with vim_mode; insert mode is the regular behavior
+    normal_mode: bool,
+    // First `g` of `gg` was typed
+    pending_g: bool,
+    show_search: bool,
+    search_input: String,
+    search_query: String,
+    search_options: search::SearchOptions,
+    // Compiled active search; matches are highlighted while it is set
+    search: Option<regex::Regex>,
+    // Matches of `search` in the history lines and the history generation they
+    // were found in; None once the search has changed. Recomputed before the
+    // next frame when stale.
+    search_matches: Option<(u64, Vec<search::SearchMatch>)>,
+    // Match that n/N last jumped to
+    search_index: Option<usize>,
+    search_error: Option<String>,
+    show_session_search: bool,
+    session_search_input: String,
+    session_index: session::SearchIndex,
+    session_hits: Vec<session::SearchHit>,
+    session_hit_index: usize,
+    // Session files that could not be indexed
+    session_index_errors: Vec<String>,
+    // Extra request body fields from the active profile
+    params: serde_json::Map<String, Value>,
+    show_profile_select: bool,
+    profile_select_index: usize,
+    pending_requests: Vec<PendingRequest>,
+    next_request_id: u64,
+    queued_prompts: requests::PromptQueue<(String, Vec<attachment::Attachment>)>,
+    last_latency_ms: Option<u64>,
+    // The config's layout as changed with the keys, kept in the state dir
+    layout: layout::LayoutConfig,
+    // Only the history pane is shown
+    zen: bool,
+    sidebar_sessions: Vec<String>,
+    sidebar_index: usize,
+    // Files attached to the next prompt
+    attachments: Vec<attachment::Attachment>,
+    show_attach: bool,
+    attach_input: String,
+    reference_completion: Option<ReferenceCompletion>,
+    // Shell command started from the input, and when
+    running_command: Option<(String, Instant)>,
 }
 
 impl App {
-    fn new() -> App {
-        let config = Config::load();
+    fn new(config: Config, profile_name: String, credentials: credentials::Credentials, keymap: keymap::Keymap, theme: theme::Theme, cli: &cli::Cli) -> App {
+        let profile = config.profile(&profile_name).cloned().unwrap_or_default();
+        let current_model = cli.model(&profile);
         let (tx, rx) = mpsc::channel(100);  // Create channel with buffer size 100
+        let layout = layout::load_state().unwrap_or_else(|| config.layout.clone());
         App {
             input: String::new(),
             response: String::new(),
-            api_url: String::from("https://qianfan.baidubce.com/v2/chat/completions"),
-            auth_token: config.auth_token.clone(),
+            api_url: cli.api_url(&profile),
+            auth: profile.auth(credentials.token.clone(), credentials.secret_key.as_deref()),
+            auth_token: credentials.token,
             show_config: false,
             show_help: false,
             config_input: String::new(),
-            visible_token: config.auth_token.clone(),
+            token_source: credentials.source,
+            passphrase: credentials.passphrase,
+            secret_key: credentials.secret_key,
             active_box: 0,
-            history: Vec::new(),
+            history: History::default(),
             scroll_offset: 0,
             config,
-            clipboard: ClipboardContext::new().unwrap_or_else(|_| panic!("无法初始化剪贴板")),
+            clipboard: ClipboardContext::new().unwrap_or_else(|_| panic!("{}", i18n::text().clipboard_init_failed)),
             response_area: None,
-            is_loading: false,
             tx,
             rx,
             input_history: Vec::new(),
             input_history_index: None,
             current_input: String::new(),
-            current_model: String::from("deepseek-r1"),  // Default model
+            current_model,
             show_model_select: false,
             model_select_index: AVAILABLE_MODELS.len() - 1,  // Default to deepseek-r1
+            show_import: false,
+            import_input: String::new(),
+            show_compare_select: false,
+            compare_select_index: 0,
+            compare_models: Vec::new(),
+            comparison: None,
+            system_prompt: None,
+            session_name: cli.session.clone(),
+            profile_name,
+            keymap,
+            theme,
+            normal_mode: false,
+            pending_g: false,
+            show_search: false,
+            search_input: String::new(),
+            search_query: String::new(),
+            search_options: search::SearchOptions::default(),
+            search: None,
+            search_matches: None,
+            search_index: None,
+            search_error: None,
+            show_session_search: false,
+            session_search_input: String::new(),
+            session_index: session::SearchIndex::default(),
+            session_hits: Vec::new(),
+            session_hit_index: 0,
+            session_index_errors: Vec::new(),
+            params: profile.params,
+            show_profile_select: false,
+            profile_select_index: 0,
+            pending_requests: Vec::new(),
+            next_request_id: 0,
+            queued_prompts: requests::PromptQueue::default(),
+            last_latency_ms: None,
+            layout,
+            zen: false,
+            sidebar_sessions: Vec::new(),
+            sidebar_index: 0,
+            attachments: Vec::new(),
+            show_attach: false,
+            attach_input: String::new(),
+            reference_completion: None,
+            running_command: None,
         }
     }
 
-    fn format_curl_command(&self, payload: &serde_json::Value) -> String {
-        let json_str = serde_json::to_string_pretty(payload).unwrap_or_default()
-            .replace("\n", "\n    ");
-        
-        format!(
-            "curl -X POST '{}' -H 'Content-Type: application/json' -H 'Authorization: Bearer {}' -d '{}'",
-            self.api_url,
-            self.auth_token,
-            json_str
-        )
+    fn restore_session(&mut self, conversation: import::Conversation, keep_model: bool) {
+        let count = conversation.messages.len();
+        *self.history = conversation.messages;
+        self.system_prompt = conversation.system_prompt;
+        if let Some(model) = conversation.model.filter(|m| !keep_model && AVAILABLE_MODELS.contains(&m.as_str())) {
+            self.current_model = model;
+        }
+        self.history.push(Message::new("system", (i18n::text().session_loaded)(
+            self.session_name.as_deref().unwrap_or_default(),
+            count,
+            &self.current_model,
+        )));
+    }
+
+    fn save_session(&mut self) {
+        let Some(name) = self.session_name.clone() else {
+            return;
+        };
+        let conversation = import::Conversation {
+            model: Some(self.current_model.clone()),
+            system_prompt: self.system_prompt.clone(),
+            messages: self.history.iter()
+                .filter(|msg| msg.kind != MessageKind::Pending)
+                .cloned()
+                .collect(),
+        };
+        if let Err(e) = session::save(&name, &conversation) {
+            self.history.push(Message::new("system", (i18n::text().session_save_error)(&e.to_string())));
+        }
+        if self.layout.sidebar {
+            self.refresh_sidebar();
+        }
+    }
+
+    // Re-reads the session list, keeping the same session selected
+    fn refresh_sidebar(&mut self) {
+        let selected = self.sidebar_sessions.get(self.sidebar_index)
+            .or(self.session_name.as_ref())
+            .cloned();
+        self.sidebar_sessions = session::list();
+        self.sidebar_index = selected
+            .and_then(|name| self.sidebar_sessions.iter().position(|s| *s == name))
+            .unwrap_or(0);
     }
 
     fn get_content_height(&self) -> u16 {
@@ -198,126 +624,552 @@ impl App {
     }
 
     async fn send_request(&mut self) -> Result<()> {
-        if self.auth_token.is_empty() {
-            self.handle_new_message(Message {
-                role: "system".to_string(),
-                content: "错误: 请先配置API认证令牌".to_string(),
-                timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-            }).await;
+        if let Some(command) = shell::parse(&self.input).map(str::to_string) {
+            self.run_command(command).await;
+            return Ok(());
+        }
+        if !self.auth.is_configured() {
+            self.handle_new_message(Message::new("system", (i18n::text().error)(i18n::text().no_auth))).await;
+            return Ok(());
+        }
+        if self.attachments.iter().any(attachment::Attachment::is_image) {
+            let models = requests::request_models(&self.compare_models, &self.current_model);
+            if let Some(model) = models.iter().find(|model| !self.config.accepts_images(model)) {
+                let notice = (i18n::text().vision_unsupported)(model);
+                self.handle_new_message(Message::new("system", notice)).await;
+                return Ok(());
+            }
+        }
+        // `@path` references are read now; the history keeps the prompt as typed
+        let references = match reference::resolve(&self.input) {
+            Ok(references) => references,
+            Err(e) => {
+                let notice = (i18n::text().reference_failed)(&format!("{:#}", e));
+                self.handle_new_message(Message::new("system", notice)).await;
+                return Ok(());
+            }
+        };
+
+        let user_input = self.take_input();
+        let mut attachments = std::mem::take(&mut self.attachments);
+        attachments.extend(references);
+        if self.config.queue_requests && self.queued_prompts.must_wait(!self.pending_requests.is_empty()) {
+            self.queued_prompts.push((user_input, attachments));
             return Ok(());
         }
+        self.start_request(user_input, attachments).await;
+        Ok(())
+    }
 
+    // Clears the input, remembering it in the input history
+    fn take_input(&mut self) -> String {
         // Save to input history if not empty and not duplicate
-        if !self.input.trim().is_empty() {
-            if self.input_history.last() != Some(&self.input) {
-                self.input_history.push(self.input.clone());
-            }
+        if !self.input.trim().is_empty() && self.input_history.last() != Some(&self.input) {
+            self.input_history.push(self.input.clone());
         }
 
         // Reset history navigation
         self.input_history_index = None;
         self.current_input.clear();
 
+        std::mem::take(&mut self.input)
+    }
+
+    // Runs `!cmd` in the background; its output is attached to the next prompt
+    async fn run_command(&mut self, command: String) {
+        if self.running_command.is_some() {
+            self.handle_new_message(Message::new("system", i18n::text().command_busy)).await;
+            return;
+        }
+        self.take_input();
+        self.running_command = Some((command.clone(), Instant::now()));
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let output = shell::run(&command).await.map_err(|e| format!("{:#}", e));
+            let _ = tx.send(ResponseEvent::Command(output)).await;
+        });
+    }
+
+    fn handle_command_output(&mut self, output: Result<attachment::Attachment, String>) {
+        self.running_command = None;
+        let notice = match output {
+            Ok(attachment) => {
+                // The full output goes with the next message, the history only shows its end
+                let notice = attachment::command_preview(&attachment, COMMAND_PREVIEW_LINES).unwrap_or_default();
+                self.attachments.push(attachment);
+                notice
+            }
+            Err(e) => e,
+        };
+        self.history.push(Message::new("system", notice));
+        if let Some(area) = self.response_area {
+            self.scroll_to_bottom(area.height);
+        }
+    }
+
+    // Sends the next queued prompt once the previous request has been answered
+    async fn send_queued(&mut self) {
+        let busy = !self.pending_requests.is_empty() || self.comparison.is_some();
+        if let Some((prompt, attachments)) = self.queued_prompts.next(busy) {
+            self.start_request(prompt, attachments).await;
+        }
+    }
+
+    async fn start_request(&mut self, user_input: String, attachments: Vec<attachment::Attachment>) {
+        // In compare mode the prompt goes to every selected model
+        let models = requests::request_models(&self.compare_models, &self.current_model);
+        let requests: Vec<(u64, String)> = models.into_iter()
+            .map(|model| {
+                let id = self.next_request_id;
+                self.next_request_id += 1;
+                (id, model)
+            })
+            .collect();
+        let first_id = requests[0].0;
+
         // Clone all needed values
         let api_url = self.api_url.clone();
-        let auth_token = self.auth_token.clone();
+        let auth = self.auth.clone();
+        let max_retries = self.config.max_retries;
         let tx = self.tx.clone();
         let current_model = self.current_model.clone();
-        let user_input = self.input.clone();
+        let params = self.params.clone();
+        let system_prompt = self.system_prompt.clone();
+        let accepts_images: Vec<String> = requests.iter()
+            .map(|(_, model)| model.clone())
+            .filter(|model| self.config.accepts_images(model))
+            .collect();
 
-        self.input.clear();
-        
         // Add user message to history
-        self.handle_new_message(Message {
-            role: "user".to_string(),
-            content: user_input.clone(),
-            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-        }).await;
+        let mut user_message = Message::new("user", user_input);
+        user_message.attachments = attachments;
+        user_message.request_id = Some(first_id);
+        self.handle_new_message(user_message).await;
+        let summary_request = self.fit_context();
+        let messages = self.build_request_messages();
+        let summary_budget = self.context_budget();
 
-        self.is_loading = true;
-        
-        // Add loading message
-        self.handle_new_message(Message {
-            role: "system".to_string(),
-            content: "正在等待响应...".to_string(),
-            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-        }).await;
+        let started = Instant::now();
+        for (id, _) in &requests {
+            self.pending_requests.push(PendingRequest { id: *id, started, retry: None });
+        }
+
+        if requests.len() > 1 {
+            self.comparison = Some(requests::Comparison::new(&requests));
+        } else {
+            // Placeholder replaced by the reply to this request
+            let mut placeholder = Message::new("system", i18n::text().waiting_response);
+            placeholder.kind = MessageKind::Pending;
+            placeholder.request_id = Some(first_id);
+            self.handle_new_message(placeholder).await;
+        }
 
         // Spawn the request as a separate task
         tokio::spawn(async move {
-            let client = reqwest::Client::builder()
-                .timeout(Duration::from_secs(30))
-                .danger_accept_invalid_certs(true)
-                .no_proxy()
-                .build()
-                .unwrap_or_default();
-
-            let payload = serde_json::json!({
-                "model": current_model,  // Use cloned value
-                "messages": [
-                    {
-                        "role": "user",
-                        "content": user_input
+            let client = api::build_client();
+            let mut messages = messages;
+            // AK/SK tokens are refreshed here, before they are used
+            let auth_token = match auth.bearer_token(&client).await {
+                Ok(token) => token,
+                Err(e) => {
+                    for (id, _) in requests {
+                        let message = Message {
+                            request_id: Some(id),
+                            ..Message::new("system", format!("{:#}", e))
+                        };
+                        let _ = tx.send(ResponseEvent::Reply { id, message }).await;
                     }
-                ]
-            });
+                    return;
+                }
+            };
+            let retry_notifier = |id: u64| {
+                let tx = tx.clone();
+                move |retry: &api::RetryAttempt| {
+                    let _ = tx.try_send(ResponseEvent::Retry {
+                        id,
+                        attempt: retry.attempt,
+                        max_attempts: retry.max_attempts,
+                        delay: retry.delay,
+                    });
+                }
+            };
 
-            // Send request
-            match client
-                .post(&api_url)
-                .header("Content-Type", "application/json")
-                .header("Authorization", format!("Bearer {}", auth_token))
-                .json(&payload)
-                .send()
-                .await {
-                    Ok(response) => {
-                        match response.text().await {
-                            Ok(text) => {
-                                if let Ok(json) = serde_json::from_str::<Value>(&text) {
-                                    if let Some(content) = json["choices"][0]["message"]["content"].as_str() {
-                                        let _ = tx.send(Message {
-                                            role: "assistant".to_string(),
-                                            content: content.to_string(),
-                                            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-                                        }).await;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                let _ = tx.send(Message {
-                                    role: "system".to_string(),
-                                    content: format!("响应解析错误: {}", e),
-                                    timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-                                }).await;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        let _ = tx.send(Message {
-                            role: "system".to_string(),
-                            content: format!("请求错误: {}", e),
-                            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-                        }).await;
+            if let Some((summary_id, transcript)) = summary_request {
+                let mut payload = api::build_payload(&current_model, context::summarize_messages(&transcript, summary_budget));
+                api::apply_params(&mut payload, &params);
+                // Its retries aren't reported: the status line shows those of the requests
+                let summary = api::send_chat_as(&client, &api_url, &auth, &auth_token, &payload, max_retries, |_: &api::RetryAttempt| {}).await
+                    .map(|reply| reply.content)
+                    .map_err(|e| e.to_string());
+                // The new summary takes the place of the previous one, if any
+                if let Ok(summary) = &summary {
+                    let system = context::system_message(system_prompt.as_deref(), Some(summary));
+                    if messages.first().is_some_and(|msg| msg["role"] == "system") {
+                        messages.remove(0);
                     }
+                    messages.splice(0..0, system);
+                }
+                let _ = tx.send(ResponseEvent::Summary { id: summary_id, summary }).await;
+            }
+
+            for (id, model) in requests {
+                let client = client.clone();
+                let api_url = api_url.clone();
+                let auth = auth.clone();
+                let auth_token = auth_token.clone();
+                let tx = tx.clone();
+                let mut messages = messages.clone();
+                if accepts_images.contains(&model) {
+                    attachment::load_images(&mut messages);
+                } else {
+                    attachment::without_images(&mut messages);
                 }
+                let mut payload = api::build_payload(&model, messages);
+                api::apply_params(&mut payload, &params);
+                let on_retry = retry_notifier(id);
+                tokio::spawn(async move {
+                    let message = match api::send_chat_as(&client, &api_url, &auth, &auth_token, &payload, max_retries, on_retry).await {
+                        Ok(reply) => Message {
+                            model: Some(model),
+                            usage: reply.usage,
+                            latency_ms: Some(reply.latency.as_millis() as u64),
+                            request_id: Some(id),
+                            ..Message::new("assistant", reply.content)
+                        },
+                        Err(e) => Message {
+                            request_id: Some(id),
+                            ..Message::new("system", e.to_string())
+                        },
+                    };
+                    let _ = tx.send(ResponseEvent::Reply { id, message }).await;
+                });
+            }
         });
+    }
 
-        Ok(())
+    // Puts a reply in place of its request's placeholder, so replies that arrive
+    // out of order still follow the turn that asked for them
+    fn handle_reply(&mut self, id: u64, message: Message) {
+        // Replies to discarded comparison columns are dropped
+        let Some(index) = self.pending_requests.iter().position(|request| request.id == id) else {
+            return;
+        };
+        self.pending_requests.remove(index);
+        if let Some(reply) = self.comparison.as_mut().and_then(|comparison| comparison.reply_mut(id)) {
+            reply.message = Some(message);
+            return;
+        }
+        if message.latency_ms.is_some() {
+            self.last_latency_ms = message.latency_ms;
+        }
+        let placeholder = self.history.iter()
+            .rposition(|msg| msg.kind == MessageKind::Pending && msg.request_id == Some(id));
+        match placeholder {
+            Some(position) => self.history[position] = message,
+            None => self.history.push(message),
+        }
+        if let Some(area) = self.response_area {
+            self.scroll_to_bottom(area.height);
+        }
+    }
+
+    fn handle_retry(&mut self, id: u64, attempt: u32, max_attempts: u32, delay: Duration) {
+        if let Some(request) = self.pending_requests.iter_mut().find(|request| request.id == id) {
+            request.retry = Some((attempt, max_attempts, Instant::now() + delay));
+        }
+    }
+
+    // Keeps the selected comparison reply and leaves compare mode, continuing
+    // the conversation with the model that wrote it
+    async fn pick_comparison(&mut self) {
+        let Some((model, message)) = self.comparison.as_ref()
+            .and_then(requests::Comparison::picked)
+            .map(|(model, message)| (model.to_string(), message.clone()))
+        else {
+            return;
+        };
+        self.close_comparison();
+        if let Some(latency) = message.latency_ms {
+            self.last_latency_ms = Some(latency);
+        }
+        self.handle_new_message(message).await;
+        self.history.push(Message::new("system", (i18n::text().compare_adopted)(&model)));
+        self.current_model = model;
+        self.compare_models.clear();
+        self.save_session();
+        self.send_queued().await;
+    }
+
+    // The prompt is dropped with its replies, so the conversation doesn't go on
+    // from an unanswered turn. It goes back to the input unless that is in use.
+    async fn discard_comparison(&mut self) {
+        let Some(first_id) = self.comparison.as_ref().map(requests::Comparison::prompt_id) else {
+            return;
+        };
+        self.close_comparison();
+        let prompt = self.history.iter()
+            .rposition(|msg| msg.role == "user" && msg.request_id == Some(first_id));
+        if let Some(index) = prompt {
+            let prompt = self.history.remove(index);
+            if self.input.is_empty() && self.attachments.is_empty() {
+                self.input = prompt.content;
+                self.attachments = prompt.attachments;
+            }
+        }
+        self.history.push(Message::new("system", i18n::text().compare_discarded));
+        self.save_session();
+        self.send_queued().await;
+    }
+
+    fn close_comparison(&mut self) {
+        if let Some(comparison) = self.comparison.take() {
+            let ids = comparison.request_ids();
+            self.pending_requests.retain(|request| !ids.contains(&request.id));
+        }
+    }
+
+    // Conversation context sent with each request: the system prompt and pinned
+    // summary followed by the user/assistant turns after the context marker.
+    // Local "system" notices are never sent.
+    fn build_request_messages(&self) -> Vec<Value> {
+        let start = self.context_start();
+        let summary = self.history[start..].iter()
+            .find(|msg| msg.kind == MessageKind::Summary)
+            .map(|msg| msg.content.as_str());
+        let mut messages: Vec<Value> = context::system_message(self.system_prompt.as_deref(), summary)
+            .into_iter()
+            .collect();
+        for msg in &self.history[start..] {
+            if msg.kind == MessageKind::Normal && (msg.role == "user" || msg.role == "assistant") {
+                messages.push(serde_json::json!({ "role": msg.role, "content": msg.request_content() }));
+            }
+        }
+        messages
+    }
+
+    // Index of the first history message after the last context marker
+    fn context_start(&self) -> usize {
+        self.history.iter()
+            .rposition(|msg| msg.kind == MessageKind::ContextMarker)
+            .map_or(0, |i| i + 1)
+    }
+
+    fn context_budget(&self) -> u64 {
+        context::request_budget(self.config.context_limit(&self.current_model))
+    }
+
+    // Moves the context marker forward until the request fits the model's context
+    // window. With the summarize strategy, returns the id of the new marker and
+    // the transcript of the turns that should be folded into the pinned summary.
+    fn fit_context(&mut self) -> Option<(u64, String)> {
+        let tokens = context::estimate_messages(&self.build_request_messages());
+        let start = self.context_start();
+        let turns: Vec<usize> = (start..self.history.len())
+            .filter(|&i| {
+                let msg = &self.history[i];
+                msg.kind == MessageKind::Normal && (msg.role == "user" || msg.role == "assistant")
+            })
+            .collect();
+        let estimates: Vec<(&str, u64)> = turns.iter()
+            .map(|&i| (self.history[i].role.as_str(), context::estimate_content(&self.history[i].request_content())))
+            .collect();
+        let dropped = context::turns_to_drop(&estimates, tokens, self.context_budget());
+        if dropped == 0 {
+            return None;
+        }
+        let cut = turns[dropped - 1] + 1;
+
+        let summarize = self.config.context_strategy == context::ContextStrategy::Summarize;
+        let transcript = summarize.then(|| {
+            let previous = self.history[start..cut].iter()
+                .find(|msg| msg.kind == MessageKind::Summary)
+                .map(|msg| msg.content.as_str());
+            let dropped_turns: Vec<(&str, &str)> = turns[..dropped].iter()
+                .map(|&i| (self.history[i].role.as_str(), self.history[i].content.as_str()))
+                .collect();
+            context::summary_transcript(previous, &dropped_turns)
+        });
+
+        // The previous marker is replaced by the new one. Pending placeholders
+        // stay, their replies still have to find them. When summarizing, the
+        // previous summary stays in context until the new one has arrived.
+        let mut cut = cut;
+        let mut previous_summary = None;
+        let mut i = 0;
+        while i < cut {
+            match self.history[i].kind {
+                MessageKind::ContextMarker => {
+                    self.history.remove(i);
+                    cut -= 1;
+                }
+                MessageKind::Summary => {
+                    let summary = self.history.remove(i);
+                    cut -= 1;
+                    previous_summary = summarize.then_some(summary);
+                }
+                _ => i += 1,
+            }
+        }
+        let mut marker = Message::new("system", self.marker_text(cut, None));
+        marker.kind = MessageKind::ContextMarker;
+        // The summary request reports back with the marker's id
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        marker.request_id = Some(id);
+        self.history.insert(cut, marker);
+        if let Some(summary) = previous_summary {
+            self.history.insert(cut + 1, summary);
+        }
+        transcript.map(|transcript| (id, transcript))
+    }
+
+    fn marker_text(&self, position: usize, summary: Option<&Result<String, String>>) -> String {
+        let count = self.history[..position].iter()
+            .filter(|msg| msg.role == "user" || msg.role == "assistant")
+            .count();
+        match (self.config.context_strategy, summary) {
+            (context::ContextStrategy::Summarize, None) => (i18n::text().marker_summarizing)(count),
+            (_, Some(Ok(_))) => (i18n::text().marker_summarized)(count),
+            (_, Some(Err(e))) => (i18n::text().marker_summary_failed)(count, e),
+            (context::ContextStrategy::Truncate, None) => (i18n::text().marker_truncated)(count),
+        }
+    }
+
+    fn handle_summary(&mut self, id: u64, summary: Result<String, String>) {
+        // A marker replaced in the meantime no longer needs its summary
+        let Some(position) = self.history.iter()
+            .position(|msg| msg.kind == MessageKind::ContextMarker && msg.request_id == Some(id))
+        else {
+            return;
+        };
+        self.history[position].content = self.marker_text(position, Some(&summary));
+        // On failure the previous summary stays in place
+        if let Ok(content) = summary {
+            // The new summary covers everything the previous one did
+            self.history.retain(|msg| msg.kind != MessageKind::Summary);
+            let position = self.history.iter()
+                .position(|msg| msg.kind == MessageKind::ContextMarker && msg.request_id == Some(id))
+                .unwrap_or(position);
+            let mut message = Message::new("system", content);
+            message.kind = MessageKind::Summary;
+            self.history.insert(position + 1, message);
+        }
+    }
+
+    fn attach_file(&mut self, path: &str) {
+        if path.trim().is_empty() {
+            return;
+        }
+        match attachment::Attachment::load(path) {
+            Ok(attachment) => self.attachments.push(attachment),
+            Err(e) => {
+                self.history.push(Message::new("system", (i18n::text().attach_failed)(&format!("{:#}", e))));
+                if let Some(area) = self.response_area {
+                    self.scroll_to_bottom(area.height);
+                }
+            }
+        }
+    }
+
+    // Completes the `@path` being typed. Returns false if the input does not
+    // end in a reference or nothing could be completed, so Tab does what the
+    // keymap binds it to as usual.
+    fn complete_reference(&mut self) -> bool {
+        if let Some(completion) = &mut self.reference_completion {
+            if self.input == format!("{}{}", completion.stem, completion.candidates[completion.index]) {
+                completion.index = (completion.index + 1) % completion.candidates.len();
+                self.input = format!("{}{}", completion.stem, completion.candidates[completion.index]);
+                return true;
+            }
+        }
+        self.reference_completion = None;
+        let Some(partial) = reference::current_token(&self.input) else {
+            return false;
+        };
+        let candidates = reference::complete(partial);
+        let stem = self.input[..self.input.len() - partial.len()].to_string();
+        let prefix = reference::common_prefix(&candidates);
+        if candidates.len() == 1 || prefix.len() > partial.len() {
+            if prefix == partial {
+                return false;
+            }
+            self.input = format!("{}{}", stem, prefix);
+        } else if !candidates.is_empty() {
+            self.input = format!("{}{}", stem, candidates[0]);
+            self.reference_completion = Some(ReferenceCompletion { stem, candidates, index: 0 });
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn get_attachment_chips(&self) -> Line<'_> {
+        let mut spans = Vec::new();
+        for attachment in &self.attachments {
+            spans.push(Span::styled(format!(" 📎 {} · {} ", attachment.name(), attachment.size()), self.theme.selected));
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::styled(i18n::text().attach_remove_hint, self.theme.muted));
+        Line::from(spans)
+    }
+
+    fn import_conversation(&mut self, spec: &str) {
+        match import::import_conversation(spec) {
+            Ok(result) => {
+                let conversation = result.conversation;
+                let count = conversation.messages.len();
+                *self.history = conversation.messages;
+                self.system_prompt = conversation.system_prompt;
+                let text = i18n::text();
+                let mut notice = (text.imported)(count, result.format.name());
+                if let Some(model) = &conversation.model {
+                    notice.push_str(&(text.imported_model)(model));
+                }
+                notice.push_str(&(text.imported_continue)(&self.current_model));
+                if self.system_prompt.is_some() {
+                    notice.push_str(text.imported_system_prompt);
+                }
+                for note in result.notes {
+                    let _ = write!(notice, "\n{}", note);
+                }
+                self.history.push(Message::new("system", notice));
+            }
+            Err(e) => {
+                self.history.push(Message::new("system", (i18n::text().import_failed)(&format!("{:#}", e))));
+            }
+        }
+        if let Some(area) = self.response_area {
+            self.scroll_to_bottom(area.height);
+        }
     }
 
     fn get_help_text(&self) -> String {
+        let text = i18n::text();
         let mut help = String::new();
-        let _ = writeln!(help, "帮助菜单:");
+        let _ = writeln!(help, "{}", text.help_heading);
         let _ = writeln!(help, "--------");
-        let _ = writeln!(help, "Alt+H    - 显示此帮助菜单");
-        let _ = writeln!(help, "Alt+C    - 配置认证令牌");
-        let _ = writeln!(help, "Alt+M    - 选择模型");
-        let _ = writeln!(help, "Alt+Y    - 复制最后一条AI回复");
-        let _ = writeln!(help, "Tab      - 切换输入框和历史框");
-        let _ = writeln!(help, "↑/↓      - 在历史框中滚动");
-        let _ = writeln!(help, "Enter    - 发送请求");
-        let _ = writeln!(help, "Ctrl+C   - 退出程序");
-        let _ = writeln!(help, "Esc      - 退出程序或关闭弹窗");
+        for action in keymap::Action::ALL {
+            let _ = writeln!(help, "{:<12} - {}", self.keymap.label(action), action.description());
+        }
+        // Fixed keys
+        for (key, description) in [("↑/↓", text.help_scroll), ("Tab", text.help_complete_reference), ("Ctrl+C", text.help_quit), ("Esc", text.help_close_popup)] {
+            let _ = writeln!(help, "{:<12} - {}", key, description);
+        }
+        if self.config.vim_mode {
+            let _ = writeln!(help, "{}", text.help_vim_heading);
+            for (key, description) in [
+                ("j/k", text.help_vim_scroll),
+                ("gg/G", text.help_vim_jump),
+                ("Ctrl+D/U", text.help_vim_half_page),
+                ("/", text.help_vim_search),
+                ("n/N", text.help_vim_next),
+                ("y", text.help_vim_copy),
+                ("i", text.help_vim_insert),
+            ] {
+                let _ = writeln!(help, "{:<12} - {}", key, description);
+            }
+        }
+        let _ = writeln!(help, "{}", text.help_keybindings);
         help
     }
 
@@ -371,11 +1223,12 @@ impl App {
         let mut formatted = String::new();
         let width = self.get_content_width();
         
-        for msg in &self.history {
+        for msg in self.history.iter() {
             let (role_display, _) = match msg.role.as_str() {
-                "user" => ("你", ""),
-                "assistant" => ("AI", ""),
-                _ => ("系统", ""),
+                _ if msg.kind == MessageKind::Summary => (i18n::text().role_summary, ""),
+                "user" => (i18n::text().role_user, ""),
+                "assistant" => (i18n::text().role_assistant, ""),
+                _ => (i18n::text().role_system, ""),
             };
             
             let header = format!("[{}] {}: ", msg.timestamp, role_display);
@@ -404,8 +1257,7 @@ impl App {
         formatted
     }
 
-    fn markdown_to_styled_text(&self, markdown: &str) -> Vec<Line> {
-        let width = self.get_content_width();
+    fn markdown_to_styled_text(&self, markdown: &str) -> Vec<Line<'_>> {
         let parser = Parser::new(markdown);
         let mut styled_lines = Vec::new();
         let mut current_line = Vec::new();
@@ -422,7 +1274,7 @@ impl App {
                     let marker = "#".repeat(level as usize);
                     current_line.push(Span::styled(
                         format!("{} ", marker),
-                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                        self.theme.heading
                     ));
                 }
                 MarkdownEvent::Start(Tag::CodeBlock(_)) => {
@@ -467,27 +1319,23 @@ impl App {
                 }
                 MarkdownEvent::Text(text) => {
                     let style = if in_code_block {
-                        Style::default()
-                            .fg(Color::Cyan)
-                            .bg(Color::Black)
+                        self.theme.code_block
                     } else {
                         Style::default()
                     };
                     current_line.push(Span::styled(text.to_string(), style));
                 }
-                MarkdownEvent::End(_) => {
-                    if !matches!(event, MarkdownEvent::End(Tag::Emphasis) | MarkdownEvent::End(Tag::Strong)) {
-                        if !current_line.is_empty() {
-                            styled_lines.push(Line::from(current_line.clone()));
-                            current_line.clear();
-                        }
-                    }
+                MarkdownEvent::Code(code) => {
+                    current_line.push(Span::styled(code.to_string(), self.theme.inline_code));
                 }
-                MarkdownEvent::SoftBreak | MarkdownEvent::HardBreak => {
-                    if !current_line.is_empty() {
-                        styled_lines.push(Line::from(current_line.clone()));
-                        current_line.clear();
-                    }
+                MarkdownEvent::End(Tag::Emphasis) | MarkdownEvent::End(Tag::Strong) => {}
+                MarkdownEvent::End(_) if !current_line.is_empty() => {
+                    styled_lines.push(Line::from(current_line.clone()));
+                    current_line.clear();
+                }
+                MarkdownEvent::SoftBreak | MarkdownEvent::HardBreak if !current_line.is_empty() => {
+                    styled_lines.push(Line::from(current_line.clone()));
+                    current_line.clear();
                 }
                 _ => {}
             }
@@ -500,108 +1348,727 @@ impl App {
         styled_lines
     }
 
-    fn get_styled_history(&self) -> Vec<Line> {
-        let mut styled_lines = Vec::new();
-        
-        for msg in &self.history {
-            let (role_display, _) = match msg.role.as_str() {
-                "user" => ("你", ""),
-                "assistant" => ("AI", ""),
-                _ => ("系统", ""),
-            };
-            
-            let header = format!("[{}] {}: ", msg.timestamp, role_display);
-            styled_lines.push(Line::from(vec![
-                Span::styled(header, Style::default().fg(Color::Green))
-            ]));
-
-            if msg.role == "assistant" {
-                let mut markdown_lines = self.markdown_to_styled_text(&msg.content);
-                for line in markdown_lines.iter_mut() {
-                    line.spans.insert(0, Span::raw("    "));
-                }
-                styled_lines.extend(markdown_lines);
-            } else {
-                styled_lines.push(Line::from(vec![
-                    Span::raw("    "),
-                    Span::raw(&msg.content)
-                ]));
+    // Model, token usage and estimated cost shown after an assistant message header
+    fn format_message_details(&self, msg: &Message) -> Option<String> {
+        let model = msg.model.as_deref()?;
+        let mut details = model.to_string();
+        if let Some(usage) = &msg.usage {
+            details.push_str(&(i18n::text().usage_details)(usage.prompt_tokens, usage.completion_tokens));
+            if let Some(cost) = self.config.estimate_cost(model, usage) {
+                let _ = write!(details, " · ¥{:.4}", cost);
             }
-
-            styled_lines.push(Line::from(""));
         }
-
-        styled_lines
-    }
-
-    fn save_config(&mut self) -> Result<()> {
-        self.config.auth_token = self.auth_token.clone();
-        self.config.save()?;
-        Ok(())
+        Some(details)
     }
 
-    fn scroll(&mut self, up: bool) {
-        if up {
-            self.scroll_offset = self.scroll_offset.saturating_sub(1);
-        } else {
-            self.scroll_offset = self.scroll_offset.saturating_add(1);
+    fn session_usage(&self) -> (u64, Option<f64>) {
+        let mut tokens = 0;
+        let mut cost = None;
+        for msg in self.history.iter() {
+            let (Some(model), Some(usage)) = (&msg.model, &msg.usage) else {
+                continue;
+            };
+            tokens += usage.total_tokens;
+            if let Some(c) = self.config.estimate_cost(model, usage) {
+                *cost.get_or_insert(0.0) += c;
+            }
         }
+        (tokens, cost)
     }
 
-    fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
-        if let Err(e) = self.clipboard.set_contents(text.to_string()) {
-            self.history.push(Message {
-                role: "system".to_string(),
-                content: format!("复制到剪贴板失败: {}", e),
-                timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-            });
-        } else {
-            self.history.push(Message {
-                role: "system".to_string(),
-                content: "已复制到剪贴板".to_string(),
-                timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-            });
-        }
-        if let Some(area) = self.response_area {
-            self.scroll_to_bottom(area.height);
+    // Estimated tokens of the context that the next request would send
+    fn context_tokens(&self) -> u64 {
+        let mut tokens = context::estimate_messages(&self.build_request_messages());
+        if !self.input.is_empty() {
+            tokens += context::estimate_tokens(&self.input);
         }
-        Ok(())
+        tokens
     }
 
-    fn update_layout(&mut self, area: Rect) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([
-                Constraint::Percentage(30),
-                Constraint::Percentage(70),
-            ])
-            .split(area);
-        self.response_area = Some(chunks[1]);
-    }
+    fn get_status_line(&self) -> Line<'_> {
+        const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+        let text = i18n::text();
+        let separator = Span::styled(" │ ", self.theme.muted);
+        let mut spans = Vec::new();
 
-    async fn handle_new_message(&mut self, message: Message) {
-        if let Some(last) = self.history.last() {
-            if last.content == "正在等待响应..." {
-                self.history.pop();
+        if self.show_search {
+            let mut prompt = format!("/{}█", self.search_input);
+            if self.search_options.regex {
+                prompt.push_str(text.search_regex);
+            }
+            if self.search_options.case_sensitive {
+                prompt.push_str(text.search_case_sensitive);
             }
+            return Line::from(vec![
+                Span::raw(prompt),
+                Span::styled(text.search_hint, self.theme.muted),
+            ]);
+        }
+        if self.config.vim_mode {
+            let (mode, style) = if self.normal_mode {
+                (" NORMAL ", self.theme.mode_normal)
+            } else {
+                (" INSERT ", self.theme.mode_insert)
+            };
+            spans.push(Span::styled(mode, style));
         }
 
-        let is_assistant = message.role == "assistant";
-        self.history.push(message);
+        // The oldest pending request drives the spinner
+        if let Some(request) = self.pending_requests.first() {
+            let elapsed = request.started.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            let mut status = (text.status_waiting)(frame, elapsed.as_secs_f64());
+            if self.pending_requests.len() > 1 {
+                status.push_str(&(text.status_requests)(self.pending_requests.len()));
+            }
+            if !self.queued_prompts.is_empty() {
+                status.push_str(&(text.status_queued)(self.queued_prompts.len()));
+            }
+            if let Some((attempt, max_attempts, retry_at)) = request.retry {
+                status.push_str(&(text.status_retry)(attempt, max_attempts));
+                let wait = retry_at.saturating_duration_since(Instant::now());
+                if !wait.is_zero() {
+                    status.push_str(&(text.status_retry_in)(wait.as_secs() + 1));
+                }
+            }
+            spans.push(Span::styled(status, self.theme.busy));
+        } else if let Some((command, started)) = &self.running_command {
+            let elapsed = started.elapsed();
+            let frame = SPINNER[(elapsed.as_millis() / 100) as usize % SPINNER.len()];
+            spans.push(Span::styled((text.status_command)(frame, command, elapsed.as_secs_f64()), self.theme.busy));
+        } else {
+            spans.push(Span::styled(text.status_ready, self.theme.ready));
+        }
+
+        spans.push(separator.clone());
+        let model = if self.compare_models.len() >= 2 {
+            (text.status_compare)(&self.compare_models.join(" / "))
+        } else {
+            self.current_model.clone()
+        };
+        spans.push(Span::styled(model, Style::default().add_modifier(Modifier::BOLD)));
+        spans.push(separator.clone());
+        spans.push(Span::raw((text.status_profile)(&self.profile_name)));
+        spans.push(separator.clone());
+        spans.push(Span::raw((text.status_session)(self.session_name.as_deref().unwrap_or(text.status_unsaved))));
+
+        if let Some(error) = &self.search_error {
+            spans.push(separator.clone());
+            spans.push(Span::styled(error.clone(), self.theme.error));
+        } else if self.search.is_some() {
+            let count = self.search_matches().len();
+            let status = match self.search_index {
+                _ if count == 0 => (text.search_no_match)(&self.search_query),
+                Some(i) => (text.search_position)(&self.search_query, i + 1, count),
+                None => (text.search_count)(&self.search_query, count),
+            };
+            spans.push(separator.clone());
+            spans.push(Span::styled(status, self.theme.warning));
+        }
+
+        let tokens = self.context_tokens();
+        let limit = self.config.context_limit(&self.current_model);
+        let context_style = if tokens * 10 >= limit * 9 {
+            self.theme.error
+        } else if tokens * 10 >= limit * 7 {
+            self.theme.warning
+        } else {
+            Style::default()
+        };
+        spans.push(separator.clone());
+        spans.push(Span::styled(
+            (text.status_context)(&context::format_tokens(tokens), &context::format_tokens(limit)),
+            context_style,
+        ));
+
+        if let Some(latency) = self.last_latency_ms {
+            spans.push(separator.clone());
+            spans.push(Span::raw((text.status_latency)(latency as f64 / 1000.0)));
+        }
+
+        let (session_tokens, session_cost) = self.session_usage();
+        if session_tokens > 0 {
+            let mut usage = (text.status_usage)(session_tokens);
+            if let Some(cost) = session_cost {
+                let _ = write!(usage, " ≈ ¥{:.4}", cost);
+            }
+            spans.push(separator);
+            spans.push(Span::raw(usage));
+        }
+
+        Line::from(spans)
+    }
+
+    fn get_styled_history(&self) -> Vec<Line<'_>> {
+        let lines = self.history_lines();
+        if self.search.is_none() {
+            return lines;
+        }
+        let mut matches = self.search_matches().iter().enumerate().peekable();
+        lines.into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let mut ranges = Vec::new();
+                let mut current = None;
+                while let Some((index, m)) = matches.next_if(|(_, m)| m.line == i) {
+                    if self.search_index == Some(index) {
+                        current = Some(ranges.len());
+                    }
+                    ranges.push((m.start, m.end));
+                }
+                if ranges.is_empty() {
+                    line
+                } else {
+                    search::highlight(line, &ranges, current, self.theme.search_match, self.theme.search_current)
+                }
+            })
+            .collect()
+    }
+
+    // History pane lines before search highlighting
+    fn history_lines(&self) -> Vec<Line<'_>> {
+        self.history.iter().flat_map(|msg| self.message_lines(msg)).collect()
+    }
+
+    // First history pane line of the message at `index`
+    fn message_line(&self, index: usize) -> usize {
+        self.history.iter().take(index).map(|msg| self.message_lines(msg).len()).sum()
+    }
+
+    fn message_lines<'a>(&'a self, msg: &'a Message) -> Vec<Line<'a>> {
+        let mut styled_lines = Vec::new();
+        let (role_display, _) = match msg.role.as_str() {
+            _ if msg.kind == MessageKind::Summary => (i18n::text().role_summary, ""),
+            "user" => (i18n::text().role_user, ""),
+            "assistant" => (i18n::text().role_assistant, ""),
+            _ => (i18n::text().role_system, ""),
+        };
         
-        // Always scroll to bottom for new messages
+        let header = format!("[{}] {}: ", msg.timestamp, role_display);
+        let mut header_spans = vec![
+            Span::styled(header, match msg.role.as_str() {
+                "user" => self.theme.user,
+                "assistant" => self.theme.assistant,
+                _ if msg.kind == MessageKind::Summary => self.theme.assistant,
+                _ => self.theme.system,
+            })
+        ];
+        if let Some(details) = self.format_message_details(msg) {
+            header_spans.push(Span::styled(details, self.theme.details));
+        }
+        if !msg.attachments.is_empty() {
+            let names: Vec<&str> = msg.attachments.iter().map(attachment::Attachment::name).collect();
+            header_spans.push(Span::styled(format!("📎 {}", names.join(", ")), self.theme.details));
+        }
+        styled_lines.push(Line::from(header_spans));
+
+        if msg.role == "assistant" || msg.kind == MessageKind::Summary {
+            let mut markdown_lines = self.markdown_to_styled_text(&msg.content);
+            for line in markdown_lines.iter_mut() {
+                line.spans.insert(0, Span::raw("    "));
+            }
+            styled_lines.extend(markdown_lines);
+        } else {
+            // Plain text, one line per line so multi-line content keeps its breaks
+            for line in msg.content.lines() {
+                styled_lines.push(Line::from(vec![
+                    Span::raw("    "),
+                    Span::raw(line)
+                ]));
+            }
+        }
+
+        styled_lines.push(Line::from(""));
+
+        styled_lines
+    }
+
+    fn open_session_search(&mut self) {
+        self.session_index_errors = self.session_index.refresh();
+        self.show_session_search = true;
+        self.update_session_search();
+    }
+
+    fn update_session_search(&mut self) {
+        self.session_hits = self.session_index.search(&self.session_search_input, 200);
+        self.session_hit_index = 0;
+    }
+
+    // Loads the session of the selected hit and scrolls to the message
+    fn open_session_hit(&mut self) {
+        let Some(hit) = self.session_hits.get(self.session_hit_index) else {
+            return;
+        };
+        let (name, message_index) = (hit.session.clone(), hit.message_index);
+        self.show_session_search = false;
+        if !self.open_session(name) {
+            return;
+        }
+
+        self.scroll_offset = self.message_line(message_index) as u16;
+        // The index finds messages containing every term, which a line search
+        // can't express; n/N follow the first term, where the snippet was taken.
+        // It matches after folding case and full-width chars, so the highlight must too.
+        self.search_query = self.session_search_input.split_whitespace().next().unwrap_or_default().to_string();
+        self.search_options = search::SearchOptions::default();
+        self.search_error = None;
+        self.search_index = None;
+        self.search = session::term_regex(&self.search_query).ok();
+        self.search_matches = None;
+        self.search_next(true);
+        if self.config.vim_mode {
+            self.set_normal_mode(true);
+        } else {
+            self.active_box = 1;
+        }
+    }
+
+    // Saves the current session and switches to `name`. Fails while requests
+    // are pending, since their replies belong to the current session.
+    fn open_session(&mut self, name: String) -> bool {
+        if !self.pending_requests.is_empty() {
+            self.history.push(Message::new("system", i18n::text().session_wait_pending));
+            return false;
+        }
+        let conversation = match session::load(&name) {
+            Ok(Some(conversation)) => conversation,
+            Ok(None) => {
+                self.history.push(Message::new("system", (i18n::text().session_missing)(&name)));
+                return false;
+            }
+            Err(e) => {
+                self.history.push(Message::new("system", (i18n::text().session_load_error)(&format!("{:#}", e))));
+                return false;
+            }
+        };
+        self.save_session();
+        self.queued_prompts.clear();
+        self.session_name = Some(name);
+        self.restore_session(conversation, false);
+        true
+    }
+
+    fn get_session_search_lines(&self, height: usize) -> Vec<Line<'_>> {
+        let mut lines = vec![
+            Line::from(format!("> {}█", self.session_search_input)),
+        ];
+        for error in &self.session_index_errors {
+            lines.push(Line::from(Span::styled((i18n::text().session_skipped)(error), self.theme.error)));
+        }
+        lines.push(Line::from(""));
+        if self.session_search_input.trim().is_empty() {
+            lines.push(Line::from(Span::styled(i18n::text().session_search_hint, self.theme.muted)));
+            return lines;
+        }
+        if self.session_hits.is_empty() {
+            lines.push(Line::from(Span::styled(i18n::text().session_search_empty, self.theme.muted)));
+            return lines;
+        }
+        let rows = height.saturating_sub(lines.len()).max(1);
+        let first = self.session_hit_index.saturating_sub(rows - 1);
+        for (i, hit) in self.session_hits.iter().enumerate().skip(first).take(rows) {
+            let date = chrono::DateTime::<chrono::Local>::from(hit.modified).format("%Y-%m-%d %H:%M");
+            let role = match hit.role.as_str() {
+                "user" => i18n::text().role_user,
+                "assistant" => i18n::text().role_assistant,
+                _ => i18n::text().role_system,
+            };
+            let style = if i == self.session_hit_index { self.theme.selected } else { Style::default() };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{} ", hit.session), self.theme.user.patch(style)),
+                Span::styled(format!("{} ", date), self.theme.details.patch(style)),
+                Span::styled(format!("{}: {}", role, hit.snippet), style),
+            ]));
+        }
+        lines
+    }
+
+    fn save_config(&mut self) -> Result<()> {
+        let Some(profile) = self.config.profile_mut(&self.profile_name) else {
+            return Ok(());
+        };
+        self.auth = profile.auth(self.auth_token.clone(), self.secret_key.as_deref());
+        match self.token_source {
+            credentials::TokenSource::Config => profile.auth_token = self.auth_token.clone(),
+            credentials::TokenSource::Encrypted => {
+                let passphrase = self.passphrase.as_deref().unwrap_or_default();
+                profile.encrypted_token = Some(credentials::encrypt(&self.auth_token, passphrase)?);
+            }
+            // The configured source wins on the next start, so there is nothing to save
+            credentials::TokenSource::Env | credentials::TokenSource::Command => {
+                self.history.push(Message::new("system", i18n::text().token_external));
+                return Ok(());
+            }
+        }
+        self.config.save()?;
+        Ok(())
+    }
+
+    fn switch_profile(&mut self, name: String, credentials: credentials::Credentials) {
+        let profile = self.config.profile(&name).cloned().unwrap_or_default();
+        self.auth = profile.auth(credentials.token.clone(), credentials.secret_key.as_deref());
+        self.auth_token = credentials.token;
+        self.token_source = credentials.source;
+        self.passphrase = credentials.passphrase;
+        self.secret_key = credentials.secret_key;
+        self.api_url = profile.endpoint.unwrap_or_else(|| api::DEFAULT_API_URL.to_string());
+        let mut unknown_model = None;
+        match profile.model {
+            Some(model) if AVAILABLE_MODELS.contains(&model.as_str()) => self.current_model = model,
+            Some(model) => unknown_model = Some(model),
+            None => {}
+        }
+        self.params = profile.params;
+
+        let mut notice = (i18n::text().profile_switched)(&name, &self.current_model, &self.api_url);
+        if let Some(model) = unknown_model {
+            notice.push('\n');
+            notice.push_str(&(i18n::text().profile_unknown_model)(&model));
+        }
+        if !self.auth.is_configured() {
+            notice.push('\n');
+            notice.push_str(i18n::text().profile_no_auth);
+        }
+        self.history.push(Message::new("system", notice));
+
+        // Remembered for the next start
+        let saved = config::save_active_profile(&self.config.path, &name);
+        self.profile_name = name;
+        if let Err(e) = saved {
+            self.history.push(Message::new("system", (i18n::text().config_save_error)(&e.to_string())));
+        }
+    }
+
+    fn get_profile_select_text(&self) -> String {
+        let mut text = String::new();
+        for (i, name) in self.config.profile_names().iter().enumerate() {
+            let prefix = if i == self.profile_select_index { "> " } else { "  " };
+            let _ = write!(text, "{}{}", prefix, name);
+            if let Some(profile) = self.config.profile(name) {
+                let model = profile.model.as_deref().unwrap_or("-");
+                let endpoint = profile.endpoint.as_deref().unwrap_or(api::DEFAULT_API_URL);
+                let _ = write!(text, "  ({} @ {})", model, endpoint);
+            }
+            if *name == self.profile_name {
+                text.push_str(i18n::text().profile_current);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn scroll(&mut self, up: bool) {
+        if up {
+            self.scroll_offset = self.scroll_offset.saturating_sub(1);
+        } else {
+            self.scroll_offset = self.scroll_offset.saturating_add(1);
+        }
+    }
+
+    fn copy_last_reply(&mut self) {
+        let content = self.history.iter()
+            .rev()
+            .find(|msg| msg.role == "assistant")
+            .map(|msg| msg.content.clone());
+        if let Some(content) = content {
+            let _ = self.copy_to_clipboard(&content);
+        }
+    }
+
+    fn set_normal_mode(&mut self, normal: bool) {
+        self.normal_mode = normal;
+        self.pending_g = false;
+        self.active_box = if normal { 1 } else { 0 };
+    }
+
+    // Lines in the history pane, in display order
+    fn history_text_lines(&self) -> Vec<String> {
+        self.history_lines()
+            .iter()
+            .map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect())
+            .collect()
+    }
+
+    fn open_search(&mut self) {
+        self.show_search = true;
+        self.search_input = self.search_query.clone();
+    }
+
+    // Starts a search for `query`, or clears the search when it is empty
+    fn run_search(&mut self, query: String) {
+        self.search_index = None;
+        self.search_error = None;
+        self.search = None;
+        self.search_matches = None;
+        self.search_query = query;
+        if self.search_query.is_empty() {
+            return;
+        }
+        match search::compile(&self.search_query, &self.search_options) {
+            Ok(regex) => {
+                self.search = Some(regex);
+                self.search_next(true);
+            }
+            Err(e) => self.search_error = Some(e),
+        }
+    }
+
+    // Finding matches renders the whole history, so it is only done when the
+    // cached matches are stale
+    fn refresh_search_matches(&mut self) {
+        let generation = self.history.generation;
+        if self.search_matches.as_ref().is_some_and(|(found_in, _)| *found_in == generation) {
+            return;
+        }
+        let matches = match &self.search {
+            Some(regex) => search::find(regex, &self.history_text_lines()),
+            None => Vec::new(),
+        };
+        self.search_matches = Some((generation, matches));
+    }
+
+    fn search_matches(&self) -> &[search::SearchMatch] {
+        self.search_matches.as_ref().map_or(&[], |(_, matches)| matches)
+    }
+
+    // Scrolls to the next or previous match, wrapping around
+    fn search_next(&mut self, forward: bool) {
+        self.refresh_search_matches();
+        let matches: Vec<usize> = self.search_matches().iter().map(|m| m.line).collect();
+        if matches.is_empty() {
+            self.search_index = None;
+            return;
+        }
+        let count = matches.len();
+        let top = self.scroll_offset as usize;
+        let next = match self.search_index {
+            Some(i) if i < count => if forward { (i + 1) % count } else { (i + count - 1) % count },
+            // The first search starts from the top of the viewport
+            _ if forward => matches.iter().position(|&line| line >= top).unwrap_or(0),
+            _ => matches.iter().rposition(|&line| line < top).unwrap_or(count - 1),
+        };
+        self.search_index = Some(next);
+        self.scroll_offset = matches[next] as u16;
+    }
+
+    // Handles a key in vim normal mode; unhandled keys fall through to the keymap
+    fn handle_normal_key(&mut self, key: &event::KeyEvent) -> bool {
+        let pending_g = std::mem::take(&mut self.pending_g);
+        let half_page = self.response_area.map_or(10, |area| area.height.saturating_sub(2) / 2).max(1);
+        let ctrl = key.modifiers.contains(event::KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('d') if ctrl => {
+                self.scroll_offset = self.scroll_offset.saturating_add(half_page);
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.scroll_offset = self.scroll_offset.saturating_sub(half_page);
+            }
+            _ if ctrl || key.modifiers.contains(event::KeyModifiers::ALT) => return false,
+            KeyCode::Char('j') => self.scroll(false),
+            KeyCode::Char('k') => self.scroll(true),
+            KeyCode::Char('g') if pending_g => self.scroll_offset = 0,
+            KeyCode::Char('g') => self.pending_g = true,
+            KeyCode::Char('G') => {
+                if let Some(area) = self.response_area {
+                    self.scroll_to_bottom(area.height);
+                }
+            }
+            KeyCode::Char('/') => self.open_search(),
+            KeyCode::Char('n') => self.search_next(true),
+            KeyCode::Char('N') => self.search_next(false),
+            KeyCode::Char('y') => self.copy_last_reply(),
+            KeyCode::Char('i') => self.set_normal_mode(false),
+            // A stray Esc does nothing, it only quits if quit is bound to it in the config
+            KeyCode::Esc if !self.keymap.is_explicit(keymap::Action::Quit) => {}
+            // Other letters must not end up in the input
+            KeyCode::Char(_) => {}
+            _ => return false,
+        }
+        true
+    }
+
+    fn copy_to_clipboard(&mut self, text: &str) -> Result<()> {
+        if let Err(e) = self.clipboard.set_contents(text.to_string()) {
+            self.history.push(Message::new("system", (i18n::text().copy_failed)(&e.to_string())));
+        } else {
+            self.history.push(Message::new("system", i18n::text().copied));
+        }
         if let Some(area) = self.response_area {
-            let content_height = self.get_content_height();
-            if content_height > area.height {
-                self.scroll_offset = content_height - area.height;
+            self.scroll_to_bottom(area.height);
+        }
+        Ok(())
+    }
+
+    fn update_layout(&mut self, area: Rect) -> layout::Areas {
+        let areas = layout::split(area, &self.layout, self.zen, &self.input, !self.attachments.is_empty());
+        self.response_area = Some(areas.history);
+        areas
+    }
+
+    // Layout changes made with the keys are kept for the next start
+    fn save_layout(&mut self) {
+        if let Err(e) = layout::save_state(&self.layout) {
+            self.history.push(Message::new("system", (i18n::text().layout_save_error)(&e.to_string())));
+        }
+    }
+
+    fn toggle_sidebar(&mut self) {
+        self.layout.sidebar = !self.layout.sidebar;
+        if self.layout.sidebar {
+            self.sidebar_sessions.clear();
+            self.refresh_sidebar();
+        } else if self.active_box == 2 {
+            self.active_box = 1;
+        }
+        self.save_layout();
+    }
+
+    fn toggle_zen(&mut self) {
+        self.zen = !self.zen;
+        if self.zen {
+            if self.config.vim_mode {
+                self.set_normal_mode(true);
             } else {
-                self.scroll_offset = 0;
+                self.active_box = 1;
             }
         }
+    }
 
-        if is_assistant {
-            self.is_loading = false;
+    // Input → history → sidebar. In vim mode the input is insert mode and the
+    // other panes are normal mode.
+    fn cycle_focus(&mut self) {
+        if self.zen {
+            self.zen = false;
+            if self.config.vim_mode {
+                self.set_normal_mode(false);
+            } else {
+                self.active_box = 0;
+            }
+            return;
         }
+        let next = match self.active_box {
+            0 => 1,
+            1 if self.layout.sidebar => 2,
+            _ => 0,
+        };
+        if self.config.vim_mode {
+            self.set_normal_mode(next != 0);
+        }
+        self.active_box = next;
+    }
+
+    // ↑/↓ (or j/k) select a session in the sidebar, Enter opens it
+    fn handle_sidebar_key(&mut self, key: &event::KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.sidebar_index = self.sidebar_index.saturating_sub(1);
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                if self.sidebar_index + 1 < self.sidebar_sessions.len() {
+                    self.sidebar_index += 1;
+                }
+            }
+            KeyCode::Enter => {
+                if let Some(name) = self.sidebar_sessions.get(self.sidebar_index).cloned() {
+                    if self.open_session(name) {
+                        if let Some(area) = self.response_area {
+                            self.scroll_to_bottom(area.height);
+                        }
+                    }
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn get_sidebar_lines(&self, height: usize) -> Vec<Line<'_>> {
+        if self.sidebar_sessions.is_empty() {
+            return vec![Line::from(Span::styled(i18n::text().sidebar_empty, self.theme.muted))];
+        }
+        let first = self.sidebar_index.saturating_sub(height.saturating_sub(1));
+        self.sidebar_sessions.iter()
+            .enumerate()
+            .skip(first)
+            .take(height.max(1))
+            .map(|(i, name)| {
+                let mut style = Style::default();
+                if self.session_name.as_ref() == Some(name) {
+                    style = style.patch(self.theme.user);
+                }
+                if self.active_box == 2 && i == self.sidebar_index {
+                    style = style.patch(self.theme.selected);
+                }
+                Line::from(Span::styled(name.as_str(), style))
+            })
+            .collect()
+    }
+
+    async fn handle_new_message(&mut self, message: Message) {
+        self.history.push(message);
+
+        // Always scroll to bottom for new messages
+        if let Some(area) = self.response_area {
+            self.scroll_to_bottom(area.height);
+        }
+    }
+
+    fn get_compare_select_text(&self) -> String {
+        let mut text = String::new();
+        for (i, model) in AVAILABLE_MODELS.iter().enumerate() {
+            let prefix = if i == self.compare_select_index { "> " } else { "  " };
+            let mark = if self.compare_models.iter().any(|m| m == model) { "[x]" } else { "[ ]" };
+            let _ = writeln!(text, "{}{} {}", prefix, mark, model);
+        }
+        text
+    }
+
+    fn toggle_compare_model(&mut self) {
+        let model = AVAILABLE_MODELS[self.compare_select_index];
+        if let Some(position) = self.compare_models.iter().position(|m| m == model) {
+            self.compare_models.remove(position);
+        } else {
+            self.compare_models.push(model.to_string());
+        }
+    }
+
+    fn close_compare_select(&mut self) {
+        self.show_compare_select = false;
+        let notice = if self.compare_models.len() >= 2 {
+            (i18n::text().compare_on)(&self.compare_models.join(", "))
+        } else {
+            self.compare_models.clear();
+            i18n::text().compare_off.to_string()
+        };
+        self.history.push(Message::new("system", notice));
+    }
+
+    fn get_comparison_lines<'a>(&'a self, reply: &'a requests::ComparisonReply) -> Vec<Line<'a>> {
+        match &reply.message {
+            Some(msg) if msg.role == "assistant" => self.markdown_to_styled_text(&msg.content),
+            Some(msg) => vec![Line::from(Span::styled(msg.content.as_str(), self.theme.error))],
+            None => vec![Line::from(Span::styled(i18n::text().waiting_response, self.theme.muted))],
+        }
+    }
+
+    fn get_comparison_title(&self, reply: &requests::ComparisonReply) -> String {
+        let mut title = reply.model.clone();
+        match &reply.message {
+            Some(msg) if msg.role == "assistant" => {
+                if let Some(latency) = msg.latency_ms {
+                    let _ = write!(title, " · {:.1}s", latency as f64 / 1000.0);
+                }
+                if let Some(usage) = &msg.usage {
+                    title.push_str(&(i18n::text().usage_details)(usage.prompt_tokens, usage.completion_tokens));
+                    if let Some(cost) = self.config.estimate_cost(&reply.model, usage) {
+                        let _ = write!(title, " · ¥{:.4}", cost);
+                    }
+                }
+            }
+            Some(_) => title.push_str(i18n::text().reply_failed),
+            None => title.push_str(i18n::text().reply_pending),
+        }
+        title
     }
 
     fn get_model_select_text(&self) -> String {
@@ -616,165 +2083,427 @@ impl App {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = cli::parse();
+    match &cli.command {
+        Some(cli::Command::Ask(args)) => std::process::exit(cli::run_ask(&cli, args).await),
+        Some(cli::Command::Batch(args)) => std::process::exit(batch::run_batch(&cli, args).await),
+        Some(cli::Command::Token { command }) => std::process::exit(credentials::run_token(&cli, command)),
+        None => {}
+    }
+
+    let config = cli.load_config().unwrap_or_else(|e| {
+        eprintln!("{}", (i18n::text().error)(&e));
+        std::process::exit(2);
+    });
+    let profile_name = cli.profile_name(&config).unwrap_or_else(|e| {
+        eprintln!("{}", (i18n::text().error)(&e));
+        std::process::exit(2);
+    });
+    let profile = config.profile(&profile_name).cloned().unwrap_or_default();
+    let credentials = credentials::resolve(&profile).unwrap_or_else(|e| {
+        eprintln!("{}", (i18n::text().error)(&format!("{:#}", e)));
+        std::process::exit(2);
+    });
+    let keymap = keymap::Keymap::new(&config.keybindings).unwrap_or_else(|e| {
+        eprintln!("{}", (i18n::text().error)(&format!("{:#}", e)));
+        std::process::exit(2);
+    });
+    let theme_name = config.theme.as_deref().unwrap_or(theme::DEFAULT_THEME);
+    let theme = theme::Theme::load(theme_name, &config.themes).unwrap_or_else(|e| {
+        eprintln!("{}", (i18n::text().error)(&format!("{:#}", e)));
+        std::process::exit(2);
+    });
+    let saved_session = match &cli.session {
+        Some(name) => session::load(name).unwrap_or_else(|e| {
+            eprintln!("{}", (i18n::text().error)(&format!("{:#}", e)));
+            std::process::exit(2);
+        }),
+        None => None,
+    };
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
 
     let mut terminal = terminal;
-    let mut app = App::new();
+    let mut app = App::new(config, profile_name, credentials, keymap, theme, &cli);
+    if let Some(conversation) = saved_session {
+        app.restore_session(conversation, cli.model.is_some());
+    }
+    if app.layout.sidebar {
+        app.refresh_sidebar();
+    }
+    if let Some(prompt) = &cli.prompt {
+        app.input = prompt.clone();
+        app.send_request().await?;
+    }
     
     loop {
-        if let Ok(message) = app.rx.try_recv() {
-            app.handle_new_message(message).await;
+        match app.rx.try_recv() {
+            Ok(ResponseEvent::Reply { id, message }) => {
+                // Replies and request errors both end the pending request
+                app.handle_reply(id, message);
+                app.save_session();
+                app.send_queued().await;
+            }
+            Ok(ResponseEvent::Summary { id, summary }) => {
+                app.handle_summary(id, summary);
+            }
+            Ok(ResponseEvent::Retry { id, attempt, max_attempts, delay }) => {
+                app.handle_retry(id, attempt, max_attempts, delay);
+            }
+            Ok(ResponseEvent::Command(output)) => {
+                app.handle_command_output(output);
+            }
+            Err(_) => {}
         }
 
         terminal.draw(|f| ui(f, &mut app))?;
 
         if event::poll(Duration::from_millis(100))? {
-            match event::read()? {
-                Event::Key(key) => {
-                    if app.show_help {
-                        if matches!(key.code, KeyCode::Esc | KeyCode::Char('h')) {
-                            app.show_help = false;
-                        }
-                    } else if app.show_config {
-                        match key.code {
-                            KeyCode::Enter => {
-                                app.auth_token = app.config_input.clone();
-                                app.visible_token = app.config_input.clone();
-                                if let Err(e) = app.save_config() {
-                                    app.history.push(Message {
-                                        role: "system".to_string(),
-                                        content: format!("配置保存错误: {}", e),
-                                        timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-                                    });
-                                }
-                                app.show_config = false;
-                                app.config_input.clear();
-                            }
-                            KeyCode::Char(c) => {
-                                app.config_input.push(c);
-                            }
-                            KeyCode::Backspace => {
-                                app.config_input.pop();
-                            }
-                            KeyCode::Esc => {
-                                app.show_config = false;
-                                app.config_input.clear();
+            if let Event::Key(key) = event::read()? {
+                if app.show_help {
+                    if matches!(key.code, KeyCode::Esc | KeyCode::Char('h'))
+                        || app.keymap.action(&key) == Some(keymap::Action::Help) {
+                        app.show_help = false;
+                    }
+                } else if app.show_config {
+                    match key.code {
+                        KeyCode::Enter => {
+                            app.auth_token = app.config_input.clone();
+                            if let Err(e) = app.save_config() {
+                                app.history.push(Message::new("system", (i18n::text().config_save_error)(&e.to_string())));
                             }
-                            _ => {}
+                            app.show_config = false;
+                            app.config_input.clear();
                         }
-                    } else if app.show_model_select {
-                        match key.code {
-                            KeyCode::Up => {
-                                if app.model_select_index > 0 {
-                                    app.model_select_index -= 1;
-                                }
-                            }
-                            KeyCode::Down => {
-                                if app.model_select_index < AVAILABLE_MODELS.len() - 1 {
-                                    app.model_select_index += 1;
-                                }
+                        KeyCode::Char(c) => {
+                            app.config_input.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            app.config_input.pop();
+                        }
+                        KeyCode::Esc => {
+                            app.show_config = false;
+                            app.config_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else if app.show_import {
+                    match key.code {
+                        KeyCode::Enter => {
+                            let spec = app.import_input.clone();
+                            app.import_conversation(&spec);
+                            app.show_import = false;
+                            app.import_input.clear();
+                        }
+                        KeyCode::Char(c) => {
+                            app.import_input.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            app.import_input.pop();
+                        }
+                        KeyCode::Esc => {
+                            app.show_import = false;
+                            app.import_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else if app.show_attach {
+                    match key.code {
+                        KeyCode::Enter => {
+                            let path = std::mem::take(&mut app.attach_input);
+                            app.attach_file(&path);
+                            app.show_attach = false;
+                        }
+                        KeyCode::Char(c) => {
+                            app.attach_input.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            app.attach_input.pop();
+                        }
+                        KeyCode::Esc => {
+                            app.show_attach = false;
+                            app.attach_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else if app.show_model_select {
+                    match key.code {
+                        KeyCode::Up if app.model_select_index > 0 => {
+                            app.model_select_index -= 1;
+                        }
+                        KeyCode::Down if app.model_select_index < AVAILABLE_MODELS.len() - 1 => {
+                            app.model_select_index += 1;
+                        }
+                        KeyCode::Enter => {
+                            app.current_model = AVAILABLE_MODELS[app.model_select_index].to_string();
+                            app.show_model_select = false;
+                            // Add confirmation message
+                            app.history.push(Message::new("system", (i18n::text().model_switched)(&app.current_model)));
+                        }
+                        KeyCode::Esc => {
+                            app.show_model_select = false;
+                        }
+                        _ => {}
+                    }
+                } else if app.show_profile_select {
+                    match key.code {
+                        KeyCode::Up if app.profile_select_index > 0 => {
+                            app.profile_select_index -= 1;
+                        }
+                        KeyCode::Down if app.profile_select_index + 1 < app.config.profile_names().len() => {
+                            app.profile_select_index += 1;
+                        }
+                        KeyCode::Enter => {
+                            app.show_profile_select = false;
+                            let name = app.config.profile_names()[app.profile_select_index].clone();
+                            let profile = app.config.profile(&name).cloned().unwrap_or_default();
+                            // The passphrase prompt needs the normal terminal screen
+                            let prompt = credentials::needs_passphrase(&profile);
+                            if prompt {
+                                suspend_terminal(&mut terminal)?;
                             }
-                            KeyCode::Enter => {
-                                app.current_model = AVAILABLE_MODELS[app.model_select_index].to_string();
-                                app.show_model_select = false;
-                                // Add confirmation message
-                                app.history.push(Message {
-                                    role: "system".to_string(),
-                                    content: format!("已切换到模型: {}", app.current_model),
-                                    timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
-                                });
+                            let result = credentials::resolve(&profile);
+                            if prompt {
+                                resume_terminal(&mut terminal)?;
                             }
-                            KeyCode::Esc => {
-                                app.show_model_select = false;
+                            match result {
+                                Ok(credentials) => app.switch_profile(name, credentials),
+                                Err(e) => app.history.push(Message::new("system", (i18n::text().profile_switch_failed)(&format!("{:#}", e)))),
                             }
-                            _ => {}
                         }
-                    } else {
-                        match key.code {
-                            KeyCode::Enter => {
-                                if app.active_box == 0 {
-                                    if let Err(e) = app.send_request().await {
-                                        app.response = format!("错误: {}", e);
-                                    }
+                        KeyCode::Esc => {
+                            app.show_profile_select = false;
+                        }
+                        _ => {}
+                    }
+                } else if app.show_compare_select {
+                    match key.code {
+                        KeyCode::Up if app.compare_select_index > 0 => {
+                            app.compare_select_index -= 1;
+                        }
+                        KeyCode::Down if app.compare_select_index < AVAILABLE_MODELS.len() - 1 => {
+                            app.compare_select_index += 1;
+                        }
+                        KeyCode::Char(' ') => {
+                            app.toggle_compare_model();
+                        }
+                        KeyCode::Enter | KeyCode::Esc => {
+                            app.close_compare_select();
+                        }
+                        _ => {}
+                    }
+                } else if let Some(comparison) = app.comparison.as_mut() {
+                    match key.code {
+                        KeyCode::Left => {
+                            comparison.select_previous();
+                        }
+                        KeyCode::Right => {
+                            comparison.select_next();
+                        }
+                        KeyCode::Up => {
+                            comparison.scroll_offset = comparison.scroll_offset.saturating_sub(1);
+                        }
+                        KeyCode::Down => {
+                            comparison.scroll_offset = comparison.scroll_offset.saturating_add(1);
+                        }
+                        KeyCode::Enter => {
+                            app.pick_comparison().await;
+                        }
+                        KeyCode::Esc => {
+                            app.discard_comparison().await;
+                        }
+                        KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            break;
+                        }
+                        _ => {}
+                    }
+                } else if app.show_session_search {
+                    match key.code {
+                        KeyCode::Up => {
+                            app.session_hit_index = app.session_hit_index.saturating_sub(1);
+                        }
+                        KeyCode::Down if app.session_hit_index + 1 < app.session_hits.len() => {
+                            app.session_hit_index += 1;
+                        }
+                        KeyCode::Enter => {
+                            app.open_session_hit();
+                        }
+                        KeyCode::Char(c) if !key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.session_search_input.push(c);
+                            app.update_session_search();
+                        }
+                        KeyCode::Backspace => {
+                            app.session_search_input.pop();
+                            app.update_session_search();
+                        }
+                        KeyCode::Esc => {
+                            app.show_session_search = false;
+                        }
+                        _ => {}
+                    }
+                } else if app.show_search {
+                    match key.code {
+                        KeyCode::Enter => {
+                            app.show_search = false;
+                            let query = std::mem::take(&mut app.search_input);
+                            app.run_search(query);
+                        }
+                        KeyCode::Char('r') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.search_options.regex = !app.search_options.regex;
+                        }
+                        KeyCode::Char('t') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.search_options.case_sensitive = !app.search_options.case_sensitive;
+                        }
+                        KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.search_input.clear();
+                        }
+                        KeyCode::Char(c) if !key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            app.search_input.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            app.search_input.pop();
+                        }
+                        KeyCode::Esc => {
+                            app.show_search = false;
+                            app.search_input.clear();
+                        }
+                        _ => {}
+                    }
+                } else if app.active_box == 2 && app.handle_sidebar_key(&key) {
+                    // Handled by the sidebar
+                } else if app.config.vim_mode && app.normal_mode && app.handle_normal_key(&key) {
+                    // Handled as a normal mode command
+                } else if app.config.vim_mode && !app.normal_mode && key.code == KeyCode::Esc {
+                    app.set_normal_mode(true);
+                } else if key.code == KeyCode::Tab && app.active_box == 0 && app.complete_reference() {
+                    // Completed an @path reference
+                } else if let Some(action) = app.keymap.action(&key) {
+                    match action {
+                        keymap::Action::Send => {
+                            if app.active_box == 0 {
+                                if let Err(e) = app.send_request().await {
+                                    app.response = (i18n::text().error)(&e.to_string());
                                 }
                             }
-                            KeyCode::Tab => {
-                                app.active_box = 1 - app.active_box;
+                        }
+                        keymap::Action::SwitchFocus => {
+                            app.cycle_focus();
+                        }
+                        keymap::Action::Help => {
+                            app.show_help = true;
+                        }
+                        keymap::Action::Config => {
+                            app.show_config = true;
+                            app.config_input = app.auth_token.clone();
+                        }
+                        keymap::Action::CopyReply => {
+                            if app.active_box == 1 {
+                                app.copy_last_reply();
                             }
-                            KeyCode::Up => {
-                                if app.active_box == 1 {
-                                    app.scroll(true);
-                                } else {
-                                    app.navigate_history(true);
-                                }
+                        }
+                        keymap::Action::Import => {
+                            app.show_import = true;
+                        }
+                        keymap::Action::SwitchProfile => {
+                            app.show_profile_select = true;
+                            app.profile_select_index = app.config.profile_names()
+                                .iter()
+                                .position(|name| *name == app.profile_name)
+                                .unwrap_or(0);
+                        }
+                        keymap::Action::CompareModels => {
+                            app.show_compare_select = true;
+                        }
+                        keymap::Action::SelectModel => {
+                            app.show_model_select = true;
+                            // Find current model index
+                            app.model_select_index = AVAILABLE_MODELS
+                                .iter()
+                                .position(|&m| m == app.current_model)
+                                .unwrap_or(AVAILABLE_MODELS.len() - 1);
+                        }
+                        keymap::Action::Search => {
+                            app.open_search();
+                        }
+                        keymap::Action::SearchSessions => {
+                            app.open_session_search();
+                        }
+                        keymap::Action::Attach => {
+                            app.show_attach = true;
+                        }
+                        keymap::Action::CycleLayout => {
+                            app.layout.style = app.layout.style.next();
+                            app.save_layout();
+                        }
+                        keymap::Action::GrowInput | keymap::Action::ShrinkInput => {
+                            app.layout.resize(action == keymap::Action::GrowInput);
+                            app.save_layout();
+                        }
+                        keymap::Action::ToggleSidebar => {
+                            app.toggle_sidebar();
+                        }
+                        keymap::Action::ZenMode => {
+                            app.toggle_zen();
+                        }
+                        keymap::Action::Quit => {
+                            break;
+                        }
+                    }
+                } else {
+                    match key.code {
+                        KeyCode::Up => {
+                            if app.active_box == 1 {
+                                app.scroll(true);
+                            } else {
+                                app.navigate_history(true);
                             }
-                            KeyCode::Down => {
-                                if app.active_box == 1 {
-                                    app.scroll(false);
+                        }
+                        KeyCode::Down => {
+                            if app.active_box == 1 {
+                                app.scroll(false);
+                            } else {
+                                if app.input_history_index.is_some() {
+                                    app.navigate_history(false);
                                 } else {
-                                    if app.input_history_index.is_some() {
-                                        app.navigate_history(false);
-                                    } else {
-                                        app.input = app.current_input.clone();
-                                        app.current_input.clear();
-                                    }
-                                }
-                            }
-                            KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-                                break;
-                            }
-                            KeyCode::Char('h') if key.modifiers.contains(event::KeyModifiers::ALT) => {
-                                app.show_help = true;
-                            }
-                            KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::ALT) => {
-                                app.show_config = true;
-                                app.config_input = app.visible_token.clone();
-                            }
-                            KeyCode::Char('y') if key.modifiers.contains(event::KeyModifiers::ALT) => {
-                                if app.active_box == 1 && app.history.len() > 0 {
-                                    let content = app.history.iter()
-                                        .rev()
-                                        .find(|msg| msg.role == "assistant")
-                                        .map(|msg| msg.content.clone());
-                                    
-                                    if let Some(content) = content {
-                                        let _ = app.copy_to_clipboard(&content);
-                                    }
-                                }
-                            }
-                            KeyCode::Char('m') if key.modifiers.contains(event::KeyModifiers::ALT) => {
-                                app.show_model_select = true;
-                                // Find current model index
-                                app.model_select_index = AVAILABLE_MODELS
-                                    .iter()
-                                    .position(|&m| m == app.current_model)
-                                    .unwrap_or(AVAILABLE_MODELS.len() - 1);
-                            }
-                            KeyCode::Char(c) => {
-                                if app.active_box == 0 {
-                                    app.input.push(c);
-                                }
-                            }
-                            KeyCode::Backspace => {
-                                if app.active_box == 0 {
-                                    app.input.pop();
+                                    app.input = app.current_input.clone();
+                                    app.current_input.clear();
                                 }
                             }
-                            KeyCode::Esc => {
-                                break;
-                            }
-                            _ => {}
                         }
+                        KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
+                            break;
+                        }
+                        KeyCode::Char('/') if app.active_box == 1 => {
+                            app.open_search();
+                        }
+                        KeyCode::Char('n') if app.active_box == 1 => {
+                            app.search_next(true);
+                        }
+                        KeyCode::Char('N') if app.active_box == 1 => {
+                            app.search_next(false);
+                        }
+                        KeyCode::Char(c) if app.active_box == 0 => {
+                            app.input.push(c);
+                        }
+                        // Backspace on an empty input removes the last attachment
+                        KeyCode::Backspace if app.active_box == 0 && app.input.is_empty() => {
+                            app.attachments.pop();
+                        }
+                        KeyCode::Backspace if app.active_box == 0 => {
+                            app.input.pop();
+                        }
+                        _ => {}
                     }
                 }
-                _ => {}
             }
         }
     }
 
+    app.save_session();
+
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
@@ -785,72 +2514,176 @@ async fn main() -> Result<()> {
 }
 
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(30),
-            Constraint::Percentage(70),
-        ])
-        .split(f.size());
+    app.refresh_search_matches();
+    let areas = app.update_layout(f.size());
 
-    app.update_layout(f.size());
+    let active_border_style = app.theme.border_active;
+    let inactive_border_style = app.theme.border;
 
-    let active_border_style = Style::default()
-        .fg(Color::Green);
-    
-    let inactive_border_style = Style::default();
+    let text = i18n::text();
+    let input_title = (text.title_input)(
+        &app.keymap.label(keymap::Action::Send),
+        &app.keymap.label(keymap::Action::Config),
+        &app.keymap.label(keymap::Action::Help),
+    );
+
+    if let Some(area) = areas.chips {
+        f.render_widget(Paragraph::new(app.get_attachment_chips()), area);
+    }
 
-    let input_title = if app.is_loading {
-        "输入 (正在等待响应...)"
+    if let Some(area) = areas.input {
+        // Keep the end of a long input in view
+        let overflow = layout::input_height(&app.input, area.width).saturating_sub(area.height);
+        let input = Paragraph::new(app.input.as_str())
+            .wrap(Wrap { trim: false })
+            .scroll((overflow, 0))
+            .block(Block::default()
+                .title(input_title)
+                .borders(Borders::ALL)
+                .border_style(if app.active_box == 0 { active_border_style } else { inactive_border_style }));
+        f.render_widget(input, area);
+    }
+
+    if let Some(area) = areas.sidebar {
+        let sidebar = Paragraph::new(app.get_sidebar_lines(area.height.saturating_sub(2) as usize))
+            .block(Block::default()
+                .title(format!("{} ({})", text.title_sidebar, app.keymap.label(keymap::Action::ToggleSidebar)))
+                .borders(Borders::ALL)
+                .border_style(if app.active_box == 2 { active_border_style } else { inactive_border_style }));
+        f.render_widget(sidebar, area);
+    }
+
+    if let Some(comparison) = &app.comparison {
+        let block = Block::default()
+            .title(text.title_comparison)
+            .borders(Borders::ALL);
+        let area = block.inner(areas.history);
+        f.render_widget(block, areas.history);
+
+        let count = comparison.replies.len() as u32;
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(vec![Constraint::Ratio(1, count); count as usize])
+            .split(area);
+        for (i, reply) in comparison.replies.iter().enumerate() {
+            let column = Paragraph::new(app.get_comparison_lines(reply))
+                .wrap(Wrap { trim: false })
+                .scroll((comparison.scroll_offset, 0))
+                .block(Block::default()
+                    .title(app.get_comparison_title(reply))
+                    .borders(Borders::ALL)
+                    .border_style(if i == comparison.selected { active_border_style } else { inactive_border_style }));
+            f.render_widget(column, columns[i]);
+        }
     } else {
-        "输入 (Enter发送, Alt+C配置, Alt+H帮助)"
-    };
+        let styled_history = app.get_styled_history();
+        let response = Paragraph::new(styled_history)
+            .scroll((app.scroll_offset, 0))
+            .block(Block::default()
+                .title(text.title_history)
+                .borders(Borders::ALL)
+                .border_style(if app.active_box == 1 { active_border_style } else { inactive_border_style }));
+        f.render_widget(response, areas.history);
+    }
 
-    let input = Paragraph::new(app.input.as_str())
-        .block(Block::default()
-            .title(input_title)
-            .borders(Borders::ALL)
-            .border_style(if app.active_box == 0 { active_border_style } else { inactive_border_style }));
-    f.render_widget(input, chunks[0]);
-
-    let styled_history = app.get_styled_history();
-    let response = Paragraph::new(styled_history)
-        .scroll((app.scroll_offset, 0))
-        .block(Block::default()
-            .title("对话历史 (↑/↓滚动)")
-            .borders(Borders::ALL)
-            .border_style(if app.active_box == 1 { active_border_style } else { inactive_border_style }));
-    f.render_widget(response, chunks[1]);
+    if let Some(area) = areas.status {
+        f.render_widget(Paragraph::new(app.get_status_line()), area);
+    }
 
     if app.show_help {
-        let area = centered_rect(60, 50, f.size());
+        let area = centered_rect(60, 70, f.size());
         let help_text = app.get_help_text();
         let help_popup = Paragraph::new(help_text)
-            .block(Block::default().title("帮助").borders(Borders::ALL));
+            .block(Block::default().title(text.title_help).borders(Borders::ALL));
         f.render_widget(Clear, area);
         f.render_widget(help_popup, area);
     }
 
     if app.show_config {
         let area = centered_rect(60, 20, f.size());
-        let config_popup = Paragraph::new(app.config_input.as_str())
-            .block(Block::default().title("输入认证令牌 (当前令牌已保存)").borders(Borders::ALL));
+        let config_popup = Paragraph::new(credentials::mask(&app.config_input))
+            .block(Block::default().title(text.title_config).borders(Borders::ALL));
         f.render_widget(Clear, area);
         f.render_widget(config_popup, area);
     }
 
+    if app.show_import {
+        let area = centered_rect(60, 20, f.size());
+        let import_popup = Paragraph::new(app.import_input.as_str())
+            .block(Block::default()
+                .title(text.title_import)
+                .borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(import_popup, area);
+    }
+
+    if app.show_attach {
+        let area = centered_rect(60, 20, f.size());
+        let attach_popup = Paragraph::new(app.attach_input.as_str())
+            .block(Block::default()
+                .title(text.title_attach)
+                .borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(attach_popup, area);
+    }
+
+    if app.show_session_search {
+        let area = centered_rect(80, 70, f.size());
+        let lines = app.get_session_search_lines(area.height.saturating_sub(2) as usize);
+        let popup = Paragraph::new(lines)
+            .block(Block::default()
+                .title((text.title_session_search)(app.session_index.session_count()))
+                .borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(popup, area);
+    }
+
+    if app.show_profile_select {
+        let area = centered_rect(70, 50, f.size());
+        let profile_popup = Paragraph::new(app.get_profile_select_text())
+            .block(Block::default()
+                .title((text.title_profile_select)(&app.profile_name))
+                .borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(profile_popup, area);
+    }
+
+    if app.show_compare_select {
+        let area = centered_rect(60, 80, f.size());
+        let compare_popup = Paragraph::new(app.get_compare_select_text())
+            .block(Block::default()
+                .title(text.title_compare_select)
+                .borders(Borders::ALL));
+        f.render_widget(Clear, area);
+        f.render_widget(compare_popup, area);
+    }
+
     if app.show_model_select {
         let area = centered_rect(60, 80, f.size());
         let model_text = app.get_model_select_text();
         let model_popup = Paragraph::new(model_text)
             .block(Block::default()
-                .title(format!("选择模型 (当前: {})", app.current_model))
+                .title((text.title_model_select)(&app.current_model))
                 .borders(Borders::ALL));
         f.render_widget(Clear, area);
         f.render_widget(model_popup, area);
     }
 }
 
+// Leaves the TUI so a prompt can be shown on the normal terminal screen
+fn suspend_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    Ok(())
+}
+
+fn resume_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    Ok(())
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::i18n;
+
+/// Puts config, data and cache under one directory instead of the XDG locations.
+pub const HOME_ENV: &str = "BD_LLM_TUI_HOME";
+
+const APP_DIR: &str = "bd-llm-tui";
+// Directory used by earlier versions for both the config and sessions
+const LEGACY_DIR: &str = "llm_tui";
+
+/// `$XDG_CONFIG_HOME/bd-llm-tui`, for config.json / config.toml.
+pub fn config_dir() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", dirs::config_dir, None)
+}
+
+/// `$XDG_DATA_HOME/bd-llm-tui`, for saved sessions.
+pub fn data_dir() -> PathBuf {
+    base_dir("XDG_DATA_HOME", dirs::data_dir, None)
+}
+
+/// `$XDG_CACHE_HOME/bd-llm-tui`, for files that can be regenerated such as IAM tokens.
+pub fn cache_dir() -> PathBuf {
+    base_dir("XDG_CACHE_HOME", dirs::cache_dir, Some("cache"))
+}
+
+fn base_dir(xdg_var: &str, platform_dir: fn() -> Option<PathBuf>, home_subdir: Option<&str>) -> PathBuf {
+    if let Some(home) = env_dir(HOME_ENV) {
+        return match home_subdir {
+            Some(subdir) => home.join(subdir),
+            None => home,
+        };
+    }
+    // The XDG spec says relative paths are invalid and should be ignored
+    let base = env_dir(xdg_var)
+        .or_else(platform_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    base.join(APP_DIR)
+}
+
+fn env_dir(var: &str) -> Option<PathBuf> {
+    std::env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+}
+
+/// `$XDG_STATE_HOME/bd-llm-tui`, for UI state kept between runs such as the layout.
+pub fn state_dir() -> PathBuf {
+    base_dir("XDG_STATE_HOME", dirs::state_dir, Some("state"))
+}
+
+/// The config file: config.toml if it exists, otherwise config.json.
+pub fn config_file() -> PathBuf {
+    let toml = config_dir().join("config.toml");
+    if toml.exists() {
+        toml
+    } else {
+        config_dir().join("config.json")
+    }
+}
+
+/// Moves the config and sessions from `~/.config/llm_tui` to the current
+/// locations, unless a config already exists there. Returns the old directory
+/// when something was moved.
+pub fn migrate_legacy() -> Result<Option<PathBuf>> {
+    let Some(legacy) = dirs::config_dir().map(|dir| dir.join(LEGACY_DIR)) else {
+        return Ok(None);
+    };
+    let config_dir = config_dir();
+    if !legacy.is_dir() || legacy == config_dir {
+        return Ok(None);
+    }
+    if ["config.json", "config.toml"].iter().any(|name| config_dir.join(name).exists()) {
+        return Ok(None);
+    }
+
+    // Sessions go first: once a config exists here the migration is not retried
+    let mut moved = move_path(&legacy.join("sessions"), &data_dir().join("sessions"))?;
+    for name in ["config.json", "config.json.bak", "config.toml", "config.toml.bak"] {
+        moved |= move_path(&legacy.join(name), &config_dir.join(name))?;
+    }
+    // Only succeeds once the directory is empty
+    let _ = fs::remove_dir(&legacy);
+    Ok(moved.then_some(legacy))
+}
+
+fn move_path(from: &Path, to: &Path) -> Result<bool> {
+    if !from.exists() || to.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // rename fails across filesystems, e.g. when the new location is a Docker volume
+    if fs::rename(from, to).is_err() {
+        copy_path(from, to)
+            .map_err(|err| {
+                // Don't leave a partial copy that would stop the next attempt
+                let _ = remove_path(to);
+                err
+            })
+            .and_then(|_| remove_path(from))
+            .with_context(|| (i18n::text().move_failed)(&from.display(), &to.display()))?;
+    }
+    Ok(true)
+}
+
+fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    if !from.is_dir() {
+        return fs::copy(from, to).map(|_| ());
+    }
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        copy_path(&entry.path(), &to.join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_and_removes_directories() {
+        let dir = std::env::temp_dir().join(format!("llm_tui_paths_test_{}", std::process::id()));
+        let from = dir.join("old/sessions");
+        fs::create_dir_all(from.join("nested")).unwrap();
+        fs::write(from.join("a.json"), "a").unwrap();
+        fs::write(from.join("nested/b.json"), "b").unwrap();
+
+        let to = dir.join("new/sessions");
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        copy_path(&from, &to).unwrap();
+        remove_path(&from).unwrap();
+        let a = fs::read_to_string(to.join("a.json")).unwrap();
+        let b = fs::read_to_string(to.join("nested/b.json")).unwrap();
+        let removed = !from.exists();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!((a.as_str(), b.as_str(), removed), ("a", "b", true));
+    }
+
+    #[test]
+    fn move_skips_existing_targets() {
+        let dir = std::env::temp_dir().join(format!("llm_tui_move_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("from"), "new").unwrap();
+        fs::write(dir.join("to"), "old").unwrap();
+        let moved = move_path(&dir.join("from"), &dir.join("to")).unwrap();
+        let missing = move_path(&dir.join("missing"), &dir.join("other")).unwrap();
+        let target = fs::read_to_string(dir.join("to")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(!moved && !missing);
+        assert_eq!(target, "old");
+    }
+}
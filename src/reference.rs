@@ -0,0 +1,115 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::attachment::Attachment;
+use crate::import;
+
+// Punctuation that usually ends the sentence rather than the path
+const TRAILING_PUNCTUATION: [char; 14] = [',', '.', ';', ':', '!', '?', ')', '"', '\'', '，', '。', '；', '：', '）'];
+
+/// Loads the files named by `@path` tokens in `prompt` as text attachments,
+/// named by the path as written. Tokens that are not existing files, such as
+/// `@someone`, are left alone.
+pub fn resolve(prompt: &str) -> Result<Vec<Attachment>> {
+    let mut attachments: Vec<Attachment> = Vec::new();
+    for token in prompt.split_whitespace() {
+        let Some(path) = token.strip_prefix('@').and_then(existing_file) else {
+            continue;
+        };
+        if attachments.iter().any(|attachment| attachment.name() == path) {
+            continue;
+        }
+        attachments.push(Attachment::load_text(path, path)?);
+    }
+    Ok(attachments)
+}
+
+fn existing_file(path: &str) -> Option<&str> {
+    let trimmed = path.trim_end_matches(TRAILING_PUNCTUATION);
+    [path, trimmed].into_iter()
+        .find(|path| !path.is_empty() && import::expand_tilde(path).is_file())
+}
+
+/// The `@path` token at the end of `input`, without the `@`.
+pub fn current_token(input: &str) -> Option<&str> {
+    input.rsplit(char::is_whitespace).next()?.strip_prefix('@')
+}
+
+/// Paths in the working directory (or the directory `partial` names) that
+/// start with `partial`, sorted. Directories end in `/`. Hidden entries are
+/// only offered once a `.` has been typed.
+pub fn complete(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(index) => partial.split_at(index + 1),
+        None => ("", partial),
+    };
+    let search_dir = if dir.is_empty() { PathBuf::from(".") } else { import::expand_tilde(dir) };
+    let Ok(entries) = fs::read_dir(search_dir) else {
+        return Vec::new();
+    };
+    let mut candidates: Vec<String> = entries.flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Longest prefix shared by all `candidates`, on a char boundary.
+pub fn common_prefix(candidates: &[String]) -> &str {
+    let Some(first) = candidates.first() else {
+        return "";
+    };
+    let mut end = first.len();
+    for candidate in &candidates[1..] {
+        end = first.char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or(end.min(candidate.len()), |((index, _), _)| index.min(end));
+    }
+    &first[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn common_prefix_of_candidates() {
+        assert_eq!(common_prefix(&[]), "");
+        assert_eq!(common_prefix(&strings(&["src/main.rs"])), "src/main.rs");
+        assert_eq!(common_prefix(&strings(&["src/main.rs", "src/mod.rs", "src/m"])), "src/m");
+        assert_eq!(common_prefix(&strings(&["abc", "xyz"])), "");
+        assert_eq!(common_prefix(&strings(&["ab", "abc"])), "ab");
+    }
+
+    #[test]
+    fn common_prefix_stops_on_char_boundary() {
+        // "中" and "串" share their first UTF-8 byte
+        assert_eq!(common_prefix(&strings(&["文件中", "文件串"])), "文件");
+    }
+
+    #[test]
+    fn current_token_is_last_word() {
+        assert_eq!(current_token("see @src/ma"), Some("src/ma"));
+        assert_eq!(current_token("@"), Some(""));
+        assert_eq!(current_token("see @src/main.rs "), None);
+        assert_eq!(current_token("mail a@b"), None);
+    }
+
+    #[test]
+    fn resolve_skips_missing_files() {
+        assert!(resolve("ask @someone about @no/such/file.rs").unwrap().is_empty());
+    }
+}
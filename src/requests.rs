@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::Message;
+
+/// Models a prompt is sent to: every model selected for comparison, or the
+/// current model when compare mode is off (fewer than two selected).
+pub fn request_models(compare_models: &[String], current_model: &str) -> Vec<String> {
+    if compare_models.len() >= 2 {
+        compare_models.to_vec()
+    } else {
+        vec![current_model.to_string()]
+    }
+}
+
+/// Prompts held back while a request is pending, sent one at a time in the
+/// order they were typed.
+pub struct PromptQueue<T> {
+    prompts: VecDeque<T>,
+}
+
+impl<T> Default for PromptQueue<T> {
+    fn default() -> Self {
+        PromptQueue { prompts: VecDeque::new() }
+    }
+}
+
+impl<T> PromptQueue<T> {
+    /// Whether a new prompt has to wait: while `busy`, or behind prompts that
+    /// are still waiting, so it can't overtake them.
+    pub fn must_wait(&self, busy: bool) -> bool {
+        busy || !self.prompts.is_empty()
+    }
+
+    pub fn push(&mut self, prompt: T) {
+        self.prompts.push_back(prompt);
+    }
+
+    /// The oldest waiting prompt, unless still `busy`.
+    pub fn next(&mut self, busy: bool) -> Option<T> {
+        if busy {
+            return None;
+        }
+        self.prompts.pop_front()
+    }
+
+    pub fn len(&self) -> usize {
+        self.prompts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.prompts.clear();
+    }
+}
+
+/// One prompt sent to several models, shown side by side until a reply is picked.
+pub struct Comparison {
+    pub replies: Vec<ComparisonReply>,
+    pub selected: usize,
+    pub scroll_offset: u16,
+}
+
+pub struct ComparisonReply {
+    pub request_id: u64,
+    pub model: String,
+    // The reply, or the error notice if the request failed
+    pub message: Option<Message>,
+}
+
+impl Comparison {
+    /// A comparison of the replies to `requests`, given as request id and model.
+    pub fn new(requests: &[(u64, String)]) -> Comparison {
+        Comparison {
+            replies: requests.iter()
+                .map(|(id, model)| ComparisonReply { request_id: *id, model: model.clone(), message: None })
+                .collect(),
+            selected: 0,
+            scroll_offset: 0,
+        }
+    }
+
+    /// The id the prompt was recorded with, that of the first request.
+    pub fn prompt_id(&self) -> u64 {
+        self.replies[0].request_id
+    }
+
+    pub fn request_ids(&self) -> Vec<u64> {
+        self.replies.iter().map(|reply| reply.request_id).collect()
+    }
+
+    pub fn reply_mut(&mut self, request_id: u64) -> Option<&mut ComparisonReply> {
+        self.replies.iter_mut().find(|reply| reply.request_id == request_id)
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.replies.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// The selected reply and its model, if it is an answer that can be
+    /// adopted rather than an error notice or still outstanding.
+    pub fn picked(&self) -> Option<(&str, &Message)> {
+        let reply = &self.replies[self.selected];
+        let message = reply.message.as_ref().filter(|msg| msg.role == "assistant")?;
+        Some((&reply.model, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_mode_needs_two_models() {
+        let models = |selected: &[&str]| {
+            let selected: Vec<String> = selected.iter().map(|m| m.to_string()).collect();
+            request_models(&selected, "current")
+        };
+        assert_eq!(models(&[]), ["current"]);
+        assert_eq!(models(&["a"]), ["current"]);
+        assert_eq!(models(&["a", "b"]), ["a", "b"]);
+    }
+
+    #[test]
+    fn queued_prompts_keep_their_order() {
+        let mut queue = PromptQueue::default();
+        assert!(!queue.must_wait(false));
+        assert!(queue.must_wait(true));
+        queue.push("first");
+        queue.push("second");
+        assert_eq!(queue.next(true), None);
+        // A prompt typed once the request is answered still goes after the waiting ones
+        assert!(queue.must_wait(false));
+        queue.push("third");
+        assert_eq!(queue.next(false), Some("first"));
+        assert_eq!(queue.next(false), Some("second"));
+        assert_eq!(queue.len(), 1);
+        assert_eq!(queue.next(false), Some("third"));
+        assert!(queue.is_empty() && !queue.must_wait(false));
+    }
+
+    #[test]
+    fn only_answers_can_be_picked() {
+        let mut comparison = Comparison::new(&[(3, "a".to_string()), (4, "b".to_string())]);
+        assert_eq!(comparison.prompt_id(), 3);
+        assert_eq!(comparison.request_ids(), [3, 4]);
+        assert!(comparison.picked().is_none());
+        assert!(comparison.reply_mut(5).is_none());
+
+        comparison.reply_mut(3).unwrap().message = Some(Message::new("system", "error"));
+        comparison.reply_mut(4).unwrap().message = Some(Message::new("assistant", "answer"));
+        assert!(comparison.picked().is_none());
+        comparison.select_next();
+        comparison.select_next();
+        assert_eq!(comparison.selected, 1);
+        let (model, message) = comparison.picked().unwrap();
+        assert_eq!((model, message.content.as_str()), ("b", "answer"));
+        comparison.select_previous();
+        comparison.select_previous();
+        assert_eq!(comparison.selected, 0);
+    }
+}
@@ -0,0 +1,127 @@
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+use regex::{Regex, RegexBuilder};
+
+use crate::i18n;
+
+/// Search options, kept between searches.
+#[derive(Default)]
+pub struct SearchOptions {
+    pub regex: bool,
+    pub case_sensitive: bool,
+}
+
+pub fn compile(query: &str, options: &SearchOptions) -> Result<Regex, String> {
+    let pattern = if options.regex { query.to_string() } else { regex::escape(query) };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(!options.case_sensitive)
+        .build()
+        .map_err(|e| (i18n::text().invalid_regex)(e.to_string().lines().last().unwrap_or_default()))
+}
+
+pub struct SearchMatch {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// All non-empty matches in `lines`, in display order.
+pub fn find(regex: &Regex, lines: &[String]) -> Vec<SearchMatch> {
+    lines.iter()
+        .enumerate()
+        .flat_map(|(line, text)| {
+            regex.find_iter(text)
+                .filter(|m| !m.is_empty())
+                .map(move |m| SearchMatch { line, start: m.start(), end: m.end() })
+        })
+        .collect()
+}
+
+/// Restyles the byte ranges `ranges` of the line's text, keeping the
+/// existing span styles outside them. `current` marks the range to emphasize.
+pub fn highlight(
+    line: Line<'_>,
+    ranges: &[(usize, usize)],
+    current: Option<usize>,
+    match_style: Style,
+    current_style: Style,
+) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    for span in line.spans {
+        let text = span.content.as_ref();
+        let span_end = offset + text.len();
+        let mut cut = offset;
+        for (i, &(start, end)) in ranges.iter().enumerate() {
+            let (start, end) = (start.max(cut), end.min(span_end));
+            if start >= end {
+                continue;
+            }
+            if start > cut {
+                spans.push(Span::styled(text[cut - offset..start - offset].to_string(), span.style));
+            }
+            let style = if current == Some(i) { current_style } else { match_style };
+            spans.push(Span::styled(text[start - offset..end - offset].to_string(), span.style.patch(style)));
+            cut = end;
+        }
+        if cut < span_end {
+            spans.push(Span::styled(text[cut - offset..].to_string(), span.style));
+        }
+        offset = span_end;
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Modifier};
+
+    fn lines(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    fn ranges(regex: &Regex, texts: &[&str]) -> Vec<(usize, usize, usize)> {
+        find(regex, &lines(texts)).into_iter().map(|m| (m.line, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn plain_search_escapes_and_ignores_case() {
+        let regex = compile("a.b", &SearchOptions::default()).unwrap();
+        assert_eq!(ranges(&regex, &["A.B axb", "", "xa.b"]), vec![(0, 0, 3), (2, 1, 4)]);
+    }
+
+    #[test]
+    fn case_sensitive_regex_search() {
+        let options = SearchOptions { regex: true, case_sensitive: true };
+        let regex = compile("a.b", &options).unwrap();
+        assert_eq!(ranges(&regex, &["A.B axb"]), vec![(0, 4, 7)]);
+        assert!(compile("(", &options).is_err());
+    }
+
+    #[test]
+    fn skips_empty_matches() {
+        let options = SearchOptions { regex: true, case_sensitive: false };
+        let regex = compile("x*", &options).unwrap();
+        assert_eq!(ranges(&regex, &["abxxc"]), vec![(0, 2, 4)]);
+    }
+
+    #[test]
+    fn highlight_splits_spans_and_keeps_styles() {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let matched = Style::default().bg(Color::Yellow);
+        let current = Style::default().bg(Color::Red);
+        let line = Line::from(vec![Span::styled("hello ", bold), Span::raw("world")]);
+        let line = highlight(line, &[(3, 8), (9, 10)], Some(1), matched, current);
+
+        let spans: Vec<(&str, Style)> = line.spans.iter().map(|span| (span.content.as_ref(), span.style)).collect();
+        assert_eq!(spans, vec![
+            ("hel", bold),
+            ("lo ", bold.patch(matched)),
+            ("wo", matched),
+            ("r", Style::default()),
+            ("l", current),
+            ("d", Style::default()),
+        ]);
+    }
+}
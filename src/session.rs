@@ -0,0 +1,309 @@
+use anyhow::{bail, Context, Result};
+use regex::{Regex, RegexBuilder};
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::i18n;
+use crate::import::Conversation;
+use crate::paths;
+
+pub fn sessions_dir() -> PathBuf {
+    paths::data_dir().join("sessions")
+}
+
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        bail!(i18n::text().session_name_empty);
+    }
+    if name.starts_with('.') || name.contains(['/', '\\']) {
+        bail!((i18n::text().session_name_invalid)(name));
+    }
+    Ok(())
+}
+
+fn session_path(name: &str) -> PathBuf {
+    sessions_dir().join(format!("{}.json", name))
+}
+
+/// Loads a saved session, returning `None` if it does not exist yet.
+pub fn load(name: &str) -> Result<Option<Conversation>> {
+    let path = session_path(name);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents = fs::read_to_string(&path)
+        .with_context(|| (i18n::text().session_read_error)(&path.display()))?;
+    let conversation = serde_json::from_str(&contents)
+        .with_context(|| (i18n::text().session_format_error)(&path.display()))?;
+    Ok(Some(conversation))
+}
+
+pub fn save(name: &str, conversation: &Conversation) -> Result<()> {
+    let path = session_path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(conversation)?;
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Names of the saved sessions, most recently changed first.
+pub fn list() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(sessions_dir()) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<(SystemTime, String)> = entries.flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                return None;
+            }
+            let name = path.file_stem()?.to_str()?.to_string();
+            let modified = entry.metadata().and_then(|meta| meta.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((modified, name))
+        })
+        .collect();
+    sessions.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    sessions.into_iter().map(|(_, name)| name).collect()
+}
+
+/// Messages of all saved sessions, normalized for searching. Sessions are
+/// re-read only when their file changes.
+#[derive(Default)]
+pub struct SearchIndex {
+    sessions: Vec<IndexedSession>,
+}
+
+struct IndexedSession {
+    name: String,
+    modified: SystemTime,
+    messages: Vec<IndexedMessage>,
+}
+
+struct IndexedMessage {
+    role: String,
+    content: String,
+    // `content` run through `normalize`, char for char
+    normalized: String,
+}
+
+pub struct SearchHit {
+    pub session: String,
+    pub modified: SystemTime,
+    pub message_index: usize,
+    pub role: String,
+    pub snippet: String,
+}
+
+impl SearchIndex {
+    /// Brings the index up to date with the sessions directory. Files that fail
+    /// to parse are skipped and returned as errors.
+    pub fn refresh(&mut self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let Ok(entries) = fs::read_dir(sessions_dir()) else {
+            self.sessions.clear();
+            return errors;
+        };
+        let mut sessions = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let modified = entry.metadata().and_then(|meta| meta.modified()).unwrap_or(SystemTime::UNIX_EPOCH);
+            if let Some(position) = self.sessions.iter().position(|s| s.name == name && s.modified == modified) {
+                sessions.push(self.sessions.swap_remove(position));
+                continue;
+            }
+            match load(name) {
+                Ok(Some(conversation)) => sessions.push(IndexedSession {
+                    name: name.to_string(),
+                    modified,
+                    messages: conversation.messages.into_iter()
+                        .map(|msg| IndexedMessage {
+                            normalized: normalize(&msg.content),
+                            role: msg.role,
+                            content: msg.content,
+                        })
+                        .collect(),
+                }),
+                Ok(None) => {}
+                Err(e) => errors.push(format!("{:#}", e)),
+            }
+        }
+        // Most recently changed first
+        sessions.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)));
+        self.sessions = sessions;
+        errors
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Messages containing every whitespace-separated term of `query`.
+    /// Matching is by substring, so CJK text needs no word segmentation.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let terms: Vec<String> = normalize(query).split_whitespace().map(str::to_string).collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+        let mut hits = Vec::new();
+        for session in &self.sessions {
+            for (message_index, msg) in session.messages.iter().enumerate() {
+                if !terms.iter().all(|term| msg.normalized.contains(term.as_str())) {
+                    continue;
+                }
+                let position = msg.normalized.find(terms[0].as_str()).unwrap_or(0);
+                hits.push(SearchHit {
+                    session: session.name.clone(),
+                    modified: session.modified,
+                    message_index,
+                    role: msg.role.clone(),
+                    snippet: snippet(&msg.content, msg.normalized[..position].chars().count()),
+                });
+                if hits.len() >= limit {
+                    return hits;
+                }
+            }
+        }
+        hits
+    }
+}
+
+// Lowercases and folds full-width ASCII and the ideographic space, mapping each
+// char to exactly one char so offsets carry over to the original text
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .map(|c| {
+            let mut lower = c.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(l), None) => l,
+                _ => c,
+            }
+        })
+        .collect()
+}
+
+/// A regex for the texts in which `search` finds `term`: case-insensitive,
+/// and accepting full-width ASCII wherever the folded char is expected.
+pub fn term_regex(term: &str) -> Result<Regex, regex::Error> {
+    let pattern: String = normalize(term).chars()
+        .map(|c| match c {
+            '!'..='~' => {
+                let full_width = char::from_u32(c as u32 + 0xFEE0).unwrap_or(c);
+                format!("(?:{}|{})", regex::escape(&c.to_string()), regex::escape(&full_width.to_string()))
+            }
+            _ => regex::escape(&c.to_string()),
+        })
+        .collect();
+    RegexBuilder::new(&pattern).case_insensitive(true).build()
+}
+
+// One line of context around the char offset `at`
+fn snippet(content: &str, at: usize) -> String {
+    const BEFORE: usize = 15;
+    const LENGTH: usize = 60;
+    let start = at.saturating_sub(BEFORE);
+    let mut snippet: String = content.chars()
+        .skip(start)
+        .take(LENGTH)
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, '…');
+    }
+    if content.chars().count() > start + LENGTH {
+        snippet.push('…');
+    }
+    snippet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index(sessions: &[(&str, &[(&str, &str)])]) -> SearchIndex {
+        SearchIndex {
+            sessions: sessions.iter()
+                .map(|(name, messages)| IndexedSession {
+                    name: name.to_string(),
+                    modified: SystemTime::UNIX_EPOCH,
+                    messages: messages.iter()
+                        .map(|(role, content)| IndexedMessage {
+                            role: role.to_string(),
+                            content: content.to_string(),
+                            normalized: normalize(content),
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+
+    fn found(index: &SearchIndex, query: &str) -> Vec<(String, usize)> {
+        index.search(query, 10).into_iter().map(|hit| (hit.session, hit.message_index)).collect()
+    }
+
+    #[test]
+    fn normalize_folds_case_and_full_width_char_for_char() {
+        assert_eq!(normalize("ＡＢｃ　Ｄ，Rust"), "abc d,rust");
+        // Chars without a one-char lowercase form are kept
+        assert_eq!(normalize("İ中文"), "İ中文");
+        let text = "Ｈｅｌｌｏ　世界 İ";
+        assert_eq!(normalize(text).chars().count(), text.chars().count());
+    }
+
+    #[test]
+    fn search_matches_every_term_after_normalizing() {
+        let index = index(&[
+            ("a", &[("user", "Rust 很好"), ("assistant", "ＲＵＳＴ　编程")]),
+            ("b", &[("user", "python 编程")]),
+        ]);
+        assert_eq!(found(&index, "rust"), [("a".to_string(), 0), ("a".to_string(), 1)]);
+        assert_eq!(found(&index, "ｒｕｓｔ 编程"), [("a".to_string(), 1)]);
+        assert_eq!(found(&index, "编程"), [("a".to_string(), 1), ("b".to_string(), 0)]);
+        assert!(found(&index, "rust python").is_empty());
+        assert!(found(&index, " \u{3000} ").is_empty());
+        assert_eq!(index.search("rust", 1).len(), 1);
+    }
+
+    #[test]
+    fn term_regex_matches_what_normalizing_matches() {
+        let regex = term_regex("ｒｕｓｔ.").unwrap();
+        for text in ["Rust.", "ＲＵＳＴ．", "rＵｓt."] {
+            assert!(regex.is_match(text), "{}", text);
+        }
+        assert!(!regex.is_match("rustx"));
+        assert!(term_regex("编程").unwrap().is_match("python 编程"));
+    }
+
+    #[test]
+    fn hit_snippet_starts_before_the_first_term() {
+        // Full-width chars are longer in UTF-8 than their folded form
+        let content = format!("{}目标 之后", "Ａ".repeat(30));
+        let index = index(&[("a", &[("user", content.as_str())])]);
+        let hits = index.search("目标", 10);
+        assert_eq!(hits[0].snippet, format!("…{}目标 之后", "Ａ".repeat(15)));
+    }
+
+    #[test]
+    fn snippet_counts_chars_not_bytes() {
+        let content: String = "一二三四五六七八九十".repeat(10);
+        let text = snippet(&content, 50);
+        let expected: String = content.chars().skip(35).take(60).collect();
+        assert_eq!(text, format!("…{}…", expected));
+        assert_eq!(snippet("中文\n内容", 0), "中文 内容");
+        assert_eq!(snippet(&content, 3), format!("{}…", content.chars().take(60).collect::<String>()));
+    }
+}
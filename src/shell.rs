@@ -0,0 +1,139 @@
+use anyhow::{bail, Context, Result};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::attachment::Attachment;
+use crate::i18n;
+
+// Commands still running after this long are killed
+const TIMEOUT: Duration = Duration::from_secs(300);
+// Only the end of longer output is kept, that is where errors usually are
+const MAX_OUTPUT_BYTES: usize = 32 * 1024;
+
+/// The command in an input of the form `!cmd` or `/run cmd`. The `!` has to
+/// start the input and be followed by the command, so that prompts such as
+/// `! why` or `!!!` still go to the model.
+pub fn parse(input: &str) -> Option<&str> {
+    let command = match input.strip_prefix('!') {
+        Some(command) if command.starts_with(|c: char| !c.is_whitespace() && c != '!') => command,
+        Some(_) => return None,
+        None => input.strip_prefix("/run").filter(|rest| rest.starts_with(char::is_whitespace))?,
+    };
+    Some(command.trim()).filter(|command| !command.is_empty())
+}
+
+/// Runs `command` in the system shell from the working directory. Stdout and
+/// stderr are captured together, in order.
+pub async fn run(command: &str) -> Result<Attachment> {
+    let text = i18n::text();
+    let child = shell_command(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| (text.command_failed)(command))?;
+    let pid = child.id();
+    let result = match tokio::time::timeout(TIMEOUT, child.wait_with_output()).await {
+        Ok(result) => result.with_context(|| (text.command_failed)(command))?,
+        Err(_) => {
+            // Dropping the child only kills the shell, not what it started
+            if let Some(pid) = pid {
+                kill_group(pid);
+            }
+            bail!("{}", (text.command_timeout)(command, TIMEOUT.as_secs()));
+        }
+    };
+
+    let mut output = String::from_utf8_lossy(&result.stdout).into_owned();
+    output.push_str(&String::from_utf8_lossy(&result.stderr));
+    Ok(Attachment::Command {
+        command: command.to_string(),
+        output: truncate_start(output),
+        exit_code: result.status.code(),
+    })
+}
+
+#[cfg(unix)]
+fn shell_command(command: &str) -> Command {
+    // Merge stderr into stdout so the output keeps its order
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(format!("exec 2>&1\n{}", command));
+    // In its own process group, so that everything it starts can be killed together
+    shell.process_group(0);
+    shell
+}
+
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    let _ = std::process::Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", pid)])
+        .stderr(Stdio::null())
+        .status();
+}
+
+#[cfg(not(unix))]
+fn shell_command(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(format!("{} 2>&1", command));
+    shell
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: u32) {}
+
+fn truncate_start(output: String) -> String {
+    if output.len() <= MAX_OUTPUT_BYTES {
+        return output;
+    }
+    let mut start = output.len() - MAX_OUTPUT_BYTES;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    format!("{}\n{}", i18n::text().command_truncated, &output[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse("!cargo build"), Some("cargo build"));
+        assert_eq!(parse("!ls  "), Some("ls"));
+        assert_eq!(parse("/run git status"), Some("git status"));
+        assert_eq!(parse("/run   ls"), Some("ls"));
+    }
+
+    #[test]
+    fn leaves_prompts_alone() {
+        assert_eq!(parse("! why does this fail"), None);
+        assert_eq!(parse("!!!"), None);
+        assert_eq!(parse("!"), None);
+        assert_eq!(parse("  !ls"), None);
+        assert_eq!(parse("see !ls"), None);
+        assert_eq!(parse("/running late"), None);
+        assert_eq!(parse("/run   "), None);
+    }
+
+    #[test]
+    fn keeps_end_of_long_output() {
+        let output = format!("{}tail", "x".repeat(MAX_OUTPUT_BYTES));
+        let truncated = truncate_start(output);
+        assert!(truncated.ends_with("tail"));
+        assert!(truncated.len() < MAX_OUTPUT_BYTES + 100);
+        assert_eq!(truncate_start("short".to_string()), "short");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn captures_output_in_order_with_exit_code() {
+        let attachment = run("echo out; echo err >&2; exit 3").await.unwrap();
+        let Attachment::Command { output, exit_code, .. } = attachment else {
+            panic!("not a command attachment");
+        };
+        assert_eq!(output, "out\nerr\n");
+        assert_eq!(exit_code, Some(3));
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{anyhow, Result};
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use crate::i18n;
+
+pub const DEFAULT_THEME: &str = "dark";
+const BUILTIN_THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+/// Styles for every colored element of the UI.
+#[derive(Clone)]
+pub struct Theme {
+    pub border: Style,
+    pub border_active: Style,
+    pub user: Style,
+    pub assistant: Style,
+    pub system: Style,
+    pub details: Style,
+    pub heading: Style,
+    pub code_block: Style,
+    pub inline_code: Style,
+    pub ready: Style,
+    pub busy: Style,
+    pub warning: Style,
+    pub error: Style,
+    pub muted: Style,
+    pub selected: Style,
+    pub search_match: Style,
+    pub search_current: Style,
+    pub mode_normal: Style,
+    pub mode_insert: Style,
+}
+
+impl Theme {
+    fn dark() -> Theme {
+        Theme {
+            border: Style::default(),
+            border_active: Style::default().fg(Color::Green),
+            user: Style::default().fg(Color::Green),
+            assistant: Style::default().fg(Color::Green),
+            system: Style::default().fg(Color::Green),
+            details: Style::default().fg(Color::DarkGray),
+            heading: Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            code_block: Style::default().fg(Color::Cyan).bg(Color::Black),
+            inline_code: Style::default().fg(Color::Cyan),
+            ready: Style::default().fg(Color::Green),
+            busy: Style::default().fg(Color::Yellow),
+            warning: Style::default().fg(Color::Yellow),
+            error: Style::default().fg(Color::Red),
+            muted: Style::default().fg(Color::DarkGray),
+            selected: Style::default().add_modifier(Modifier::REVERSED),
+            search_match: Style::default().fg(Color::Black).bg(Color::Yellow),
+            search_current: Style::default().fg(Color::Black).bg(Color::LightRed).add_modifier(Modifier::BOLD),
+            mode_normal: Style::default().fg(Color::Black).bg(Color::Blue).add_modifier(Modifier::BOLD),
+            mode_insert: Style::default().fg(Color::Black).bg(Color::Green).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    // For terminals with a light background: no yellow or cyan text, no black fill
+    fn light() -> Theme {
+        Theme {
+            border: Style::default(),
+            border_active: Style::default().fg(Color::Blue),
+            user: Style::default().fg(Color::Blue),
+            assistant: Style::default().fg(Color::Magenta),
+            system: Style::default().fg(Color::DarkGray),
+            details: Style::default().fg(Color::DarkGray),
+            heading: Style::default().fg(Color::Blue).add_modifier(Modifier::BOLD),
+            code_block: Style::default().fg(Color::Black).bg(Color::Gray),
+            inline_code: Style::default().fg(Color::Red),
+            ready: Style::default().fg(Color::Green),
+            busy: Style::default().fg(Color::Magenta),
+            warning: Style::default().fg(Color::Magenta),
+            error: Style::default().fg(Color::Red),
+            muted: Style::default().fg(Color::DarkGray),
+            selected: Style::default().add_modifier(Modifier::REVERSED),
+            search_match: Style::default().fg(Color::Black).bg(Color::LightYellow),
+            search_current: Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD),
+            mode_normal: Style::default().fg(Color::White).bg(Color::Blue).add_modifier(Modifier::BOLD),
+            mode_insert: Style::default().fg(Color::White).bg(Color::Green).add_modifier(Modifier::BOLD),
+        }
+    }
+
+    fn high_contrast() -> Theme {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Theme {
+            border: Style::default().fg(Color::White),
+            border_active: bold.fg(Color::LightYellow),
+            user: bold.fg(Color::LightCyan),
+            assistant: bold.fg(Color::LightGreen),
+            system: bold.fg(Color::White),
+            details: Style::default().fg(Color::White),
+            heading: bold.fg(Color::LightYellow).add_modifier(Modifier::UNDERLINED),
+            code_block: Style::default().fg(Color::Black).bg(Color::White),
+            inline_code: bold.fg(Color::LightCyan),
+            ready: bold.fg(Color::LightGreen),
+            busy: bold.fg(Color::LightYellow),
+            warning: bold.fg(Color::LightYellow),
+            error: bold.fg(Color::LightRed),
+            muted: Style::default().fg(Color::White),
+            selected: bold.add_modifier(Modifier::REVERSED),
+            search_match: bold.fg(Color::Black).bg(Color::LightYellow),
+            search_current: bold.fg(Color::Black).bg(Color::LightCyan).add_modifier(Modifier::UNDERLINED),
+            mode_normal: bold.fg(Color::Black).bg(Color::LightCyan),
+            mode_insert: bold.fg(Color::Black).bg(Color::LightGreen),
+        }
+    }
+
+    // For NO_COLOR: emphasis only through text attributes
+    fn no_color() -> Theme {
+        let plain = Style::default();
+        let bold = plain.add_modifier(Modifier::BOLD);
+        let reversed = plain.add_modifier(Modifier::REVERSED);
+        Theme {
+            border: plain,
+            border_active: bold,
+            user: bold,
+            assistant: bold,
+            system: bold,
+            details: plain,
+            heading: bold.add_modifier(Modifier::UNDERLINED),
+            code_block: plain,
+            inline_code: plain,
+            ready: plain,
+            busy: bold,
+            warning: bold,
+            error: bold,
+            muted: plain,
+            selected: reversed,
+            search_match: reversed,
+            search_current: reversed.add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            mode_normal: reversed,
+            mode_insert: reversed,
+        }
+    }
+
+    fn builtin(name: &str) -> Option<Theme> {
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+
+    /// The theme called `name`, built-in or from `themes` in the config.
+    /// `NO_COLOR` overrides any theme.
+    pub fn load(name: &str, themes: &BTreeMap<String, ThemeConfig>) -> Result<Theme> {
+        if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return Ok(Theme::no_color());
+        }
+        let Some(config) = themes.get(name) else {
+            return Theme::builtin(name).ok_or_else(|| {
+                let mut names: Vec<&str> = BUILTIN_THEMES.to_vec();
+                names.extend(themes.keys().map(String::as_str));
+                anyhow!((i18n::text().theme_not_found)(name, &names.join(", ")))
+            });
+        };
+        let base = config.base.as_deref().unwrap_or(DEFAULT_THEME);
+        let mut theme = Theme::builtin(base)
+            .ok_or_else(|| anyhow!((i18n::text().theme_bad_base)(name, &BUILTIN_THEMES.join(", "))))?;
+        for (element, spec) in &config.styles {
+            let style = spec.to_style().map_err(|e| anyhow!((i18n::text().theme_bad_style)(name, element, &e)))?;
+            *theme.element_mut(element)
+                .ok_or_else(|| anyhow!((i18n::text().theme_unknown_element)(name, element)))? = style;
+        }
+        Ok(theme)
+    }
+
+    fn element_mut(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "border" => &mut self.border,
+            "border_active" => &mut self.border_active,
+            "user" => &mut self.user,
+            "assistant" => &mut self.assistant,
+            "system" => &mut self.system,
+            "details" => &mut self.details,
+            "heading" => &mut self.heading,
+            "code_block" => &mut self.code_block,
+            "inline_code" => &mut self.inline_code,
+            "ready" => &mut self.ready,
+            "busy" => &mut self.busy,
+            "warning" => &mut self.warning,
+            "error" => &mut self.error,
+            "muted" => &mut self.muted,
+            "selected" => &mut self.selected,
+            "search_match" => &mut self.search_match,
+            "search_current" => &mut self.search_current,
+            "mode_normal" => &mut self.mode_normal,
+            "mode_insert" => &mut self.mode_insert,
+            _ => return None,
+        })
+    }
+}
+
+/// A user theme: a built-in base plus per-element styles.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThemeConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base: Option<String>,
+    #[serde(flatten)]
+    styles: BTreeMap<String, StyleSpec>,
+}
+
+/// Either a foreground color (`"red"`, `"#b58900"`, `"208"`) or a full style.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum StyleSpec {
+    Color(String),
+    Style {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fg: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bg: Option<String>,
+        #[serde(default)]
+        bold: bool,
+        #[serde(default)]
+        italic: bool,
+        #[serde(default)]
+        underline: bool,
+        #[serde(default)]
+        reversed: bool,
+    },
+}
+
+impl StyleSpec {
+    fn to_style(&self) -> Result<Style> {
+        let color = |name: &str| Color::from_str(name).map_err(|_| anyhow!((i18n::text().color_unknown)(name)));
+        match self {
+            StyleSpec::Color(fg) => Ok(Style::default().fg(color(fg)?)),
+            StyleSpec::Style { fg, bg, bold, italic, underline, reversed } => {
+                let mut style = Style::default();
+                if let Some(fg) = fg {
+                    style = style.fg(color(fg)?);
+                }
+                if let Some(bg) = bg {
+                    style = style.bg(color(bg)?);
+                }
+                for (enabled, modifier) in [
+                    (bold, Modifier::BOLD),
+                    (italic, Modifier::ITALIC),
+                    (underline, Modifier::UNDERLINED),
+                    (reversed, Modifier::REVERSED),
+                ] {
+                    if *enabled {
+                        style = style.add_modifier(modifier);
+                    }
+                }
+                Ok(style)
+            }
+        }
+    }
+}